@@ -0,0 +1,209 @@
+use crate::bitmap::WriteError;
+use crate::disk::WritableDiskPartition;
+use crate::param::Params;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+use core::ops::Range;
+
+/// Allocates clusters on an in-memory copy of the Allocation Bitmap.
+///
+/// The changes are not written to the partition until [`ClusterAllocator::flush()`] is called so
+/// the caller can decide when to write them.
+pub(crate) struct ClusterAllocator {
+    params: Arc<Params>,
+    chain: Vec<usize>,
+    data: Vec<u8>,
+    dirty: Option<Range<usize>>,
+    next: usize, // All clusters before this are allocated.
+}
+
+impl ClusterAllocator {
+    /// `chain` is the clusters of the Allocation Bitmap and `data` is its content.
+    pub fn new(params: Arc<Params>, chain: Vec<usize>, data: Vec<u8>) -> Self {
+        Self {
+            params,
+            chain,
+            data,
+            dirty: None,
+            next: 2,
+        }
+    }
+
+    /// Allocates `count` clusters starting at `first` if all of them are free.
+    pub fn allocate_at(&mut self, first: usize, count: usize) -> bool {
+        if !self.is_free(first, count) {
+            return false;
+        }
+
+        self.set(first..(first + count), true);
+
+        true
+    }
+
+    /// Allocates `count` contiguous clusters and returns the first one.
+    pub fn allocate_contiguous(&mut self, count: usize) -> Option<usize> {
+        let end = self.params.cluster_count + 2;
+        let mut first = self.next;
+
+        while first + count <= end {
+            // Find the next allocated cluster in the range.
+            let used = (first..(first + count)).rev().find(|&c| self.is_used(c));
+
+            if let Some(c) = used {
+                first = c + 1;
+                continue;
+            }
+
+            self.set(first..(first + count), true);
+
+            return Some(first);
+        }
+
+        None
+    }
+
+    /// Allocates a single cluster.
+    pub fn allocate_one(&mut self) -> Option<usize> {
+        let end = self.params.cluster_count + 2;
+        let cluster = (self.next..end).find(|&c| !self.is_used(c))?;
+
+        self.set(cluster..(cluster + 1), true);
+
+        Some(cluster)
+    }
+
+    /// Allocates `count` clusters, preferring the clusters after `hint`.
+    pub fn allocate(&mut self, count: usize, hint: usize) -> Option<Vec<usize>> {
+        let end = self.params.cluster_count + 2;
+        let hint = if (2..end).contains(&hint) { hint } else { 2 };
+        let mut clusters: Vec<usize> = (hint..end)
+            .filter(|&c| !self.is_used(c))
+            .take(count)
+            .collect();
+
+        // Check if the remaining clusters are available before the hint.
+        let remaining = count - clusters.len();
+        let available = (self.next..hint)
+            .filter(|&c| !self.is_used(c))
+            .take(remaining)
+            .count();
+
+        if available != remaining {
+            return None;
+        }
+
+        for &c in &clusters {
+            self.set(c..(c + 1), true);
+        }
+
+        // All free clusters after the hint are already taken so the rest will come from before it.
+        while clusters.len() < count {
+            clusters.push(self.allocate_one().unwrap());
+        }
+
+        Some(clusters)
+    }
+
+    /// Returns the in-memory copy of the Allocation Bitmap.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Marks `cluster` as free.
+    pub fn free(&mut self, cluster: usize) {
+        self.set(cluster..(cluster + 1), false);
+    }
+
+    /// Writes the changes to the partition.
+    pub fn flush<P: WritableDiskPartition>(&mut self, disk: &P) -> Result<(), WriteError<P>> {
+        let dirty = match self.dirty.take() {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        let cluster_size = self.params.cluster_size() as usize;
+        let mut i = dirty.start;
+
+        while i < dirty.end {
+            // The chain may not cover the whole bitmap on a corrupted volume.
+            let cluster = match self.chain.get(i / cluster_size) {
+                Some(&v) => v,
+                None => {
+                    self.dirty = Some(i..dirty.end);
+                    return Err(WriteError::NoCluster(i));
+                }
+            };
+
+            let offset = match self.params.cluster_offset(cluster) {
+                Some(v) => v + (i % cluster_size) as u64,
+                None => {
+                    self.dirty = Some(i..dirty.end);
+                    return Err(WriteError::InvalidCluster(cluster));
+                }
+            };
+
+            let end = min(dirty.end, (i / cluster_size + 1) * cluster_size);
+
+            if let Err(e) = disk.write_all(offset, &self.data[i..end]) {
+                self.dirty = Some(i..dirty.end);
+                return Err(WriteError::WriteFailed(offset, e));
+            }
+
+            i = end;
+        }
+
+        Ok(())
+    }
+
+    fn is_free(&self, first: usize, count: usize) -> bool {
+        first >= 2
+            && (first + count) <= (self.data.len() * 8 + 2)
+            && (first..(first + count)).all(|c| !self.is_used(c))
+    }
+
+    fn is_used(&self, cluster: usize) -> bool {
+        let i = cluster - 2;
+
+        match self.data.get(i / 8) {
+            Some(&v) => (v & (1 << (i % 8))) != 0,
+            None => true,
+        }
+    }
+
+    fn set(&mut self, clusters: Range<usize>, allocated: bool) {
+        if clusters.is_empty() {
+            return;
+        }
+
+        // Update the bits.
+        let first = (clusters.start - 2) / 8;
+        let last = (clusters.end - 3) / 8;
+
+        for c in clusters.clone() {
+            let i = c - 2;
+
+            if allocated {
+                self.data[i / 8] |= 1 << (i % 8);
+            } else {
+                self.data[i / 8] &= !(1 << (i % 8));
+            }
+        }
+
+        self.dirty = match self.dirty.take() {
+            Some(v) => Some(min(v.start, first)..max(v.end, last + 1)),
+            None => Some(first..(last + 1)),
+        };
+
+        // Update the hint.
+        if !allocated {
+            self.next = min(self.next, clusters.start);
+        } else if clusters.start == self.next {
+            let end = self.params.cluster_count + 2;
+
+            self.next = (clusters.end..end)
+                .find(|&c| !self.is_used(c))
+                .unwrap_or(end);
+        }
+    }
+}
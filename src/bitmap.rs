@@ -0,0 +1,273 @@
+use crate::allocator::ClusterAllocator;
+use crate::cluster::ClustersReader;
+use crate::disk::{DiskPartition, WritableDiskPartition};
+use crate::entries::ClusterAllocation;
+use crate::fat::Fat;
+use crate::param::Params;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use spin::Mutex;
+use thiserror::Error;
+
+/// Represents an Allocation Bitmap.
+pub(crate) struct Bitmap {
+    allocator: Mutex<ClusterAllocator>,
+}
+
+impl Bitmap {
+    pub fn load<D: DiskPartition>(
+        disk: &D,
+        params: &Arc<Params>,
+        fat: &Fat,
+        alloc: &ClusterAllocation,
+    ) -> Result<Self, LoadError> {
+        // Check size.
+        let len = params.cluster_count.div_ceil(8) as u64;
+
+        if alloc.data_length() < len {
+            return Err(LoadError::InvalidDataLength);
+        }
+
+        // Read the bitmap.
+        let first_cluster = alloc.first_cluster();
+        let mut reader =
+            match ClustersReader::new(disk, params, fat, first_cluster, Some(len), None) {
+                Ok(v) => v,
+                Err(e) => return Err(LoadError::CreateClustersReaderFailed(e)),
+            };
+
+        let mut data = vec![0u8; len as usize];
+
+        if let Err(e) = reader.read_exact(&mut data) {
+            return Err(LoadError::ReadFailed(e));
+        }
+
+        let chain = fat.get_cluster_chain(first_cluster).collect();
+
+        Ok(Self {
+            allocator: Mutex::new(ClusterAllocator::new(params.clone(), chain, data)),
+        })
+    }
+
+    /// Returns a copy of the bitmap. The bit for cluster #2 is the lowest bit of the first byte.
+    pub fn snapshot(&self) -> Vec<u8> {
+        self.allocator.lock().data().to_vec()
+    }
+
+    /// Allocates `count` clusters starting at `first` if all of them are free.
+    pub fn alloc_at<P: WritableDiskPartition>(
+        &self,
+        disk: &P,
+        first: usize,
+        count: usize,
+    ) -> Result<bool, WriteError<P>> {
+        let mut allocator = self.allocator.lock();
+
+        if !allocator.allocate_at(first, count) {
+            return Ok(false);
+        }
+
+        allocator.flush(disk)?;
+
+        Ok(true)
+    }
+
+    /// Allocates `count` contiguous clusters and returns the first one.
+    pub fn alloc_contiguous<P: WritableDiskPartition>(
+        &self,
+        disk: &P,
+        count: usize,
+    ) -> Result<Option<usize>, WriteError<P>> {
+        let mut allocator = self.allocator.lock();
+        let first = allocator.allocate_contiguous(count);
+
+        allocator.flush(disk)?;
+
+        Ok(first)
+    }
+
+    /// Allocates `count` clusters, preferring the clusters after `hint`.
+    pub fn alloc<P: WritableDiskPartition>(
+        &self,
+        disk: &P,
+        count: usize,
+        hint: usize,
+    ) -> Result<Option<Vec<usize>>, WriteError<P>> {
+        let mut allocator = self.allocator.lock();
+        let clusters = allocator.allocate(count, hint);
+
+        allocator.flush(disk)?;
+
+        Ok(clusters)
+    }
+
+    /// Marks `clusters` as free.
+    pub fn free<P: WritableDiskPartition>(
+        &self,
+        disk: &P,
+        clusters: &[usize],
+    ) -> Result<(), WriteError<P>> {
+        let mut allocator = self.allocator.lock();
+
+        for &c in clusters {
+            allocator.free(c);
+        }
+
+        allocator.flush(disk)
+    }
+
+    /// Allocates clusters until `chain` has `count` clusters. Returns a new NoFatChain flag.
+    ///
+    /// This will try to keep the clusters contiguous. If it is not possible the whole chain will
+    /// be converted to FAT chain.
+    pub fn grow<P: WritableDiskPartition>(
+        &self,
+        disk: &P,
+        fat: &Fat,
+        chain: &mut Vec<usize>,
+        no_fat_chain: bool,
+        count: usize,
+    ) -> Result<bool, AllocError<P>> {
+        let need = count - chain.len();
+
+        // Try to keep the clusters contiguous so we don't need to touch the FAT.
+        if chain.is_empty() {
+            match self.alloc_contiguous(disk, need) {
+                Ok(Some(first)) => {
+                    chain.extend(first..(first + need));
+                    return Ok(true);
+                }
+                Ok(None) => {}
+                Err(e) => return Err(AllocError::UpdateBitmapFailed(e)),
+            }
+        } else if no_fat_chain {
+            let next = chain[chain.len() - 1] + 1;
+
+            match self.alloc_at(disk, next, need) {
+                Ok(true) => {
+                    chain.extend(next..(next + need));
+                    return Ok(true);
+                }
+                Ok(false) => {}
+                Err(e) => return Err(AllocError::UpdateBitmapFailed(e)),
+            }
+
+            // Convert the existing clusters to a FAT chain.
+            if let Err(e) = fat.set_chain(disk, chain) {
+                return Err(AllocError::UpdateFatFailed(e));
+            }
+        }
+
+        // Allocate the clusters from anywhere.
+        let hint = chain.last().map_or(2, |&c| c + 1);
+        let clusters = match self.alloc(disk, need, hint) {
+            Ok(Some(v)) => v,
+            Ok(None) => return Err(AllocError::NoSpace),
+            Err(e) => return Err(AllocError::UpdateBitmapFailed(e)),
+        };
+
+        // Link the new clusters, including the previous last cluster.
+        let start = chain.len().saturating_sub(1);
+
+        chain.extend(clusters);
+
+        if let Err(e) = fat.set_chain(disk, &chain[start..]) {
+            return Err(AllocError::UpdateFatFailed(e));
+        }
+
+        Ok(false)
+    }
+
+    /// Frees clusters until `chain` has `count` clusters.
+    pub fn shrink<P: WritableDiskPartition>(
+        &self,
+        disk: &P,
+        fat: &Fat,
+        chain: &mut Vec<usize>,
+        no_fat_chain: bool,
+        count: usize,
+    ) -> Result<(), AllocError<P>> {
+        let freed = chain.split_off(count);
+
+        if !no_fat_chain {
+            if let Some(&last) = chain.last() {
+                if let Err(e) = fat.set(disk, last, 0xffffffff) {
+                    return Err(AllocError::UpdateFatFailed(e));
+                }
+            }
+        }
+
+        if let Err(e) = self.free(disk, &freed) {
+            return Err(AllocError::UpdateBitmapFailed(e));
+        }
+
+        Ok(())
+    }
+}
+
+/// Represents an error for [`Bitmap::load()`].
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error("invalid DataLength")]
+    InvalidDataLength,
+
+    #[error("cannot create a clusters reader")]
+    CreateClustersReaderFailed(#[source] crate::cluster::NewError),
+
+    #[error("cannot read the bitmap")]
+    ReadFailed(#[source] crate::io::ReadError),
+}
+
+/// Represents an error when writing the Allocation Bitmap fails.
+#[derive(Error)]
+pub enum WriteError<P: DiskPartition> {
+    #[error("no cluster for byte {0:#x} of the Allocation Bitmap")]
+    NoCluster(usize),
+
+    #[error("cluster #{0} of the Allocation Bitmap is not valid")]
+    InvalidCluster(usize),
+
+    #[error("cannot write the data at {0:#x}")]
+    WriteFailed(u64, #[source] P::Err),
+}
+
+impl<P: DiskPartition> Debug for WriteError<P> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoCluster(arg0) => f.debug_tuple("NoCluster").field(arg0).finish(),
+            Self::InvalidCluster(arg0) => f.debug_tuple("InvalidCluster").field(arg0).finish(),
+            Self::WriteFailed(arg0, arg1) => f
+                .debug_tuple("WriteFailed")
+                .field(arg0)
+                .field(arg1)
+                .finish(),
+        }
+    }
+}
+
+/// Represents an error when allocating or freeing clusters fails.
+#[derive(Error)]
+pub enum AllocError<P: DiskPartition> {
+    #[error("not enough free space")]
+    NoSpace,
+
+    #[error("cannot update Allocation Bitmap")]
+    UpdateBitmapFailed(#[source] WriteError<P>),
+
+    #[error("cannot update FAT")]
+    UpdateFatFailed(#[source] crate::fat::WriteError<P>),
+}
+
+impl<P: DiskPartition> Debug for AllocError<P> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoSpace => write!(f, "NoSpace"),
+            Self::UpdateBitmapFailed(arg0) => {
+                f.debug_tuple("UpdateBitmapFailed").field(arg0).finish()
+            }
+            Self::UpdateFatFailed(arg0) => f.debug_tuple("UpdateFatFailed").field(arg0).finish(),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use byteorder::{ByteOrder, LE};
+
+/// Represents the fields of the Main Boot Sector (or the Backup Boot Sector if it was used).
+///
+/// All values are as stored on the disk. The volume has been validated with these values when
+/// [`Root`][crate::Root] was opened.
+#[derive(Debug, Clone, Copy)]
+pub struct BootSector {
+    jump_boot: [u8; 3],
+    file_system_name: [u8; 8],
+    partition_offset: u64,
+    volume_length: u64,
+    fat_offset: u32,
+    fat_length: u32,
+    cluster_heap_offset: u32,
+    cluster_count: u32,
+    first_cluster_of_root_directory: u32,
+    volume_serial_number: u32,
+    file_system_revision: u16,
+    volume_flags: u16,
+    bytes_per_sector_shift: u8,
+    sectors_per_cluster_shift: u8,
+    number_of_fats: u8,
+    drive_select: u8,
+    percent_in_use: u8,
+}
+
+impl BootSector {
+    pub(crate) fn parse(boot: &[u8]) -> Self {
+        Self {
+            jump_boot: boot[..3].try_into().unwrap(),
+            file_system_name: boot[3..11].try_into().unwrap(),
+            partition_offset: LE::read_u64(&boot[64..]),
+            volume_length: LE::read_u64(&boot[72..]),
+            fat_offset: LE::read_u32(&boot[80..]),
+            fat_length: LE::read_u32(&boot[84..]),
+            cluster_heap_offset: LE::read_u32(&boot[88..]),
+            cluster_count: LE::read_u32(&boot[92..]),
+            first_cluster_of_root_directory: LE::read_u32(&boot[96..]),
+            volume_serial_number: LE::read_u32(&boot[100..]),
+            file_system_revision: LE::read_u16(&boot[104..]),
+            volume_flags: LE::read_u16(&boot[106..]),
+            bytes_per_sector_shift: boot[108],
+            sectors_per_cluster_shift: boot[109],
+            number_of_fats: boot[110],
+            drive_select: boot[111],
+            percent_in_use: boot[112],
+        }
+    }
+
+    pub fn jump_boot(&self) -> [u8; 3] {
+        self.jump_boot
+    }
+
+    /// Returns FileSystemName, which is always `EXFAT   `.
+    pub fn file_system_name(&self) -> [u8; 8] {
+        self.file_system_name
+    }
+
+    /// Returns PartitionOffset in sectors. Zero means it should be ignored.
+    pub fn partition_offset(&self) -> u64 {
+        self.partition_offset
+    }
+
+    /// Returns VolumeLength in sectors.
+    pub fn volume_length(&self) -> u64 {
+        self.volume_length
+    }
+
+    /// Returns FatOffset in sectors.
+    pub fn fat_offset(&self) -> u32 {
+        self.fat_offset
+    }
+
+    /// Returns FatLength in sectors.
+    pub fn fat_length(&self) -> u32 {
+        self.fat_length
+    }
+
+    /// Returns ClusterHeapOffset in sectors.
+    pub fn cluster_heap_offset(&self) -> u32 {
+        self.cluster_heap_offset
+    }
+
+    pub fn cluster_count(&self) -> u32 {
+        self.cluster_count
+    }
+
+    pub fn first_cluster_of_root_directory(&self) -> u32 {
+        self.first_cluster_of_root_directory
+    }
+
+    pub fn volume_serial_number(&self) -> u32 {
+        self.volume_serial_number
+    }
+
+    /// Returns FileSystemRevision with the major revision in the high byte (e.g. `0x0100` for
+    /// 1.00).
+    pub fn file_system_revision(&self) -> u16 {
+        self.file_system_revision
+    }
+
+    /// Returns VolumeFlags at the time the volume was opened.
+    pub fn volume_flags(&self) -> u16 {
+        self.volume_flags
+    }
+
+    pub fn bytes_per_sector_shift(&self) -> u8 {
+        self.bytes_per_sector_shift
+    }
+
+    pub fn sectors_per_cluster_shift(&self) -> u8 {
+        self.sectors_per_cluster_shift
+    }
+
+    pub fn number_of_fats(&self) -> u8 {
+        self.number_of_fats
+    }
+
+    pub fn drive_select(&self) -> u8 {
+        self.drive_select
+    }
+
+    /// Returns PercentInUse, which is `0xff` if not available.
+    pub fn percent_in_use(&self) -> u8 {
+        self.percent_in_use
+    }
+}
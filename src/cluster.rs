@@ -1,16 +1,29 @@
 use crate::disk::DiskPartition;
 use crate::fat::Fat;
+use crate::io::{Read, ReadError, Seek, SeekError, SeekFrom, TypedReadError};
 use crate::param::Params;
-use std::cmp::min;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::min;
+use spin::Mutex;
 use thiserror::Error;
 
 /// Struct to read all data in a cluster chain.
+#[derive(Clone)]
 pub struct ClustersReader<D, P> {
     disk: D,
     params: P,
-    chain: Vec<usize>,
+    chain: Chain,
     data_length: u64,
+    valid_data_length: u64,
     offset: u64,
+    cache: Option<Arc<ClusterCache>>,
+    readahead: usize,
+    ahead: Vec<u8>,
+    ahead_offset: u64,
+    last_end: u64, // Position after the last read.
 }
 
 impl<D, P: AsRef<Params>> ClustersReader<D, P> {
@@ -35,13 +48,28 @@ impl<D, P: AsRef<Params>> ClustersReader<D, P> {
                 _ => return Err(NewError::InvalidDataLength),
             };
 
-            // FIXME: Use div_ceil once https://github.com/rust-lang/rust/issues/88581 stabilized.
-            let count = (data_length + cluster_size - 1) / cluster_size;
-            let chain: Vec<usize> = (first_cluster..(first_cluster + count as usize)).collect();
+            // The clusters must be within the Cluster Heap. The count may not fit in usize on a
+            // 32-bit target.
+            let available = params
+                .as_ref()
+                .cluster_count
+                .saturating_sub(first_cluster - 2);
+            let count = match usize::try_from(data_length.div_ceil(cluster_size)) {
+                Ok(v) if v <= available => v,
+                _ => return Err(NewError::InvalidDataLength),
+            };
+
+            let chain = Chain::Contiguous {
+                start: first_cluster,
+                count,
+            };
 
             (chain, data_length)
         } else {
-            let chain: Vec<usize> = fat.get_cluster_chain(first_cluster).collect();
+            let chain = match fat.get_valid_cluster_chain(first_cluster) {
+                Ok(v) => v,
+                Err(e) => return Err(NewError::CorruptClusterChain(e)),
+            };
 
             if chain.is_empty() {
                 return Err(NewError::InvalidFirstCluster);
@@ -61,7 +89,7 @@ impl<D, P: AsRef<Params>> ClustersReader<D, P> {
                 }
             };
 
-            (chain, data_length)
+            (Chain::Fat(chain), data_length)
         };
 
         Ok(Self {
@@ -69,12 +97,23 @@ impl<D, P: AsRef<Params>> ClustersReader<D, P> {
             params,
             chain,
             data_length,
+            valid_data_length: data_length,
             offset: 0,
+            cache: None,
+            readahead: 0,
+            ahead: Vec::new(),
+            ahead_offset: 0,
+            last_end: 0,
         })
     }
 
     pub fn cluster(&self) -> usize {
-        self.chain[(self.offset / self.params.as_ref().cluster_size()) as usize]
+        self.chain
+            .get((self.offset / self.params.as_ref().cluster_size()) as usize)
+    }
+
+    pub fn cluster_size(&self) -> u64 {
+        self.params.as_ref().cluster_size()
     }
 }
 
@@ -83,6 +122,35 @@ impl<D, P> ClustersReader<D, P> {
         self.data_length
     }
 
+    pub fn first_cluster(&self) -> usize {
+        self.chain.get(0)
+    }
+
+    /// Returns the number of clusters to read.
+    pub fn cluster_count(&self) -> usize {
+        self.chain.len()
+    }
+
+    /// Sets the length of the initialized data. The data after this will be read as zeros.
+    pub fn set_valid_data_length(&mut self, v: u64) {
+        self.valid_data_length = min(v, self.data_length);
+        self.ahead.clear();
+    }
+
+    /// Sets the cache to lookup before reading the clusters from the partition.
+    pub(crate) fn set_cache(&mut self, cache: Option<Arc<ClusterCache>>) {
+        self.cache = cache;
+    }
+
+    /// Sets the number of contiguous clusters to read at once when the reads are sequential. Zero
+    /// disables read-ahead, which is the default.
+    ///
+    /// This has no effect when the cache is enabled.
+    pub fn set_readahead(&mut self, clusters: usize) {
+        self.readahead = clusters;
+        self.ahead.clear();
+    }
+
     pub fn seek(&mut self, off: u64) -> bool {
         if off > self.data_length {
             return false;
@@ -99,40 +167,116 @@ impl<D, P> ClustersReader<D, P> {
     pub fn stream_position(&self) -> u64 {
         self.offset
     }
+
+    /// Returns the number of bytes from the current position to the end of the data.
+    pub fn remaining(&self) -> u64 {
+        self.data_length.saturating_sub(self.offset)
+    }
 }
 
 impl<D: DiskPartition, P: AsRef<Params>> ClustersReader<D, P> {
-    pub fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        use std::io::{Error, ErrorKind};
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, ReadError> {
+        match self.read_typed(buf) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Same as [`ClustersReader::read()`] but the error from the partition is not boxed.
+    pub fn read_typed(&mut self, buf: &mut [u8]) -> Result<usize, TypedReadError<D::Err>> {
+        let amount = self.read_next(buf)?;
+
+        self.last_end = self.offset;
+
+        Ok(amount)
+    }
 
+    pub fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), ReadError> {
+        Read::read_exact(self, buf)
+    }
+
+    fn read_next(&mut self, buf: &mut [u8]) -> Result<usize, TypedReadError<D::Err>> {
         // Check if the actual read is required.
-        if buf.is_empty() || self.offset == self.data_length {
+        if buf.is_empty() || self.offset >= self.data_length {
             return Ok(0);
         }
 
+        // The data after ValidDataLength is undefined so we need to return zeros.
+        if self.offset >= self.valid_data_length {
+            let amount = min(buf.len() as u64, self.data_length - self.offset) as usize;
+
+            buf[..amount].fill(0);
+            self.offset += amount as u64;
+
+            return Ok(amount);
+        }
+
+        // Serve sequential reads from the read-ahead buffer.
+        if self.readahead != 0 && self.cache.is_none() {
+            if let Some(amount) = self.read_ahead(buf)? {
+                return Ok(amount);
+            }
+        }
+
         // Get remaining data in the current cluster.
         let params = self.params.as_ref();
         let cluster_size = params.cluster_size();
-        let cluster_remaining = cluster_size - self.offset % cluster_size;
-        let remaining = min(cluster_remaining, self.data_length - self.offset);
+        let mut index = (self.offset / cluster_size) as usize;
+        let mut available = cluster_size - self.offset % cluster_size;
+
+        // Read the whole cluster through the cache if enabled.
+        if let Some(cache) = &self.cache {
+            let cluster = self.chain.get(index);
+            let start = (self.offset % cluster_size) as usize;
+            let remaining = min(available, self.valid_data_length - self.offset);
+            let amount = min(buf.len() as u64, remaining) as usize;
+
+            if !cache.read(cluster, start, &mut buf[..amount]) {
+                let offset = match params.cluster_offset(cluster) {
+                    Some(v) => v,
+                    None => return Err(TypedReadError::InvalidCluster(cluster)),
+                };
+
+                let mut data = vec![0u8; cluster_size as usize];
+
+                if let Err(e) = self.disk.read_exact(offset, &mut data) {
+                    return Err(TypedReadError::ReadFailed(offset, e));
+                }
+
+                buf[..amount].copy_from_slice(&data[start..(start + amount)]);
+                cache.insert(cluster, data);
+            }
+
+            self.offset += amount as u64;
+
+            return Ok(amount);
+        }
 
         // Get the offset in the partition.
-        let cluster = self.chain[(self.offset / cluster_size) as usize];
+        let cluster = self.chain.get(index);
         let offset = match params.cluster_offset(cluster) {
             Some(v) => v + self.offset % cluster_size,
-            None => {
-                return Err(Error::new(
-                    ErrorKind::Other,
-                    format!("cluster #{cluster} is not available"),
-                ));
-            }
+            None => return Err(TypedReadError::InvalidCluster(cluster)),
         };
 
+        // Include the next clusters if they are contiguous so we can read all of them at once.
+        while available < buf.len() as u64 && (index + 1) < self.chain.len() {
+            let next = self.chain.get(index + 1);
+
+            if next != self.chain.get(index) + 1 || params.cluster_offset(next).is_none() {
+                break;
+            }
+
+            index += 1;
+            available += cluster_size;
+        }
+
         // Read image.
-        let amount = min(buf.len(), remaining as usize);
+        let remaining = min(available, self.valid_data_length - self.offset);
+        let amount = min(buf.len() as u64, remaining) as usize;
 
         if let Err(e) = self.disk.read_exact(offset, &mut buf[..amount]) {
-            return Err(Error::new(ErrorKind::Other, Box::new(e)));
+            return Err(TypedReadError::ReadFailed(offset, e));
         }
 
         self.offset += amount as u64;
@@ -140,19 +284,292 @@ impl<D: DiskPartition, P: AsRef<Params>> ClustersReader<D, P> {
         Ok(amount)
     }
 
-    pub fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<(), std::io::Error> {
-        while !buf.is_empty() {
-            let n = self.read(buf)?;
+    /// Copies the data at the current position from the read-ahead buffer, filling it first if the
+    /// read is sequential. Returns [`None`] if the data should be read directly.
+    fn read_ahead(&mut self, buf: &mut [u8]) -> Result<Option<usize>, TypedReadError<D::Err>> {
+        let params = self.params.as_ref();
+        let cluster_size = params.cluster_size();
+        let end = self.ahead_offset + self.ahead.len() as u64;
 
-            if n == 0 {
-                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+        if self.offset < self.ahead_offset || self.offset >= end {
+            // A large read already covers the contiguous clusters so don't buffer it.
+            if self.offset != self.last_end
+                || buf.len() as u64 >= cluster_size * self.readahead as u64
+            {
+                return Ok(None);
             }
 
-            buf = &mut buf[n..];
+            // Collect the contiguous clusters.
+            let index = (self.offset / cluster_size) as usize;
+            let first = self.chain.get(index);
+            let offset = match params.cluster_offset(first) {
+                Some(v) => v,
+                None => return Err(TypedReadError::InvalidCluster(first)),
+            };
+
+            let mut count = 1;
+
+            while count < self.readahead && (index + count) < self.chain.len() {
+                let next = self.chain.get(index + count);
+
+                if next != first + count || params.cluster_offset(next).is_none() {
+                    break;
+                }
+
+                count += 1;
+            }
+
+            // Fill the buffer up to ValidDataLength.
+            let start = index as u64 * cluster_size;
+            let len = min(count as u64 * cluster_size, self.valid_data_length - start);
+
+            self.ahead.resize(len as usize, 0);
+            self.ahead_offset = start;
+
+            if let Err(e) = self.disk.read_exact(offset, &mut self.ahead) {
+                self.ahead.clear();
+                return Err(TypedReadError::ReadFailed(offset, e));
+            }
         }
 
+        // Copy the data.
+        let start = (self.offset - self.ahead_offset) as usize;
+        let amount = min(buf.len(), self.ahead.len() - start);
+
+        buf[..amount].copy_from_slice(&self.ahead[start..(start + amount)]);
+        self.offset += amount as u64;
+
+        Ok(Some(amount))
+    }
+}
+
+impl<D: DiskPartition, P: AsRef<Params>> Read for ClustersReader<D, P> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ReadError> {
+        Self::read(self, buf)
+    }
+}
+
+impl<D, P> Seek for ClustersReader<D, P> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, SeekError> {
+        // Get absolute offset.
+        let o = seek_offset(self.offset, self.data_length, pos)?;
+
+        // Seeking past the end is allowed. The subsequent reads will return zero bytes.
+        if o > self.data_length {
+            self.offset = o;
+        } else if !Self::seek(self, o) {
+            return Err(SeekError::InvalidOffset(o));
+        }
+
+        Ok(o)
+    }
+
+    fn rewind(&mut self) -> Result<(), SeekError> {
+        Self::rewind(self);
         Ok(())
     }
+
+    fn stream_position(&mut self) -> Result<u64, SeekError> {
+        Ok(self.offset)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<D: DiskPartition, P: AsRef<Params>> std::io::Read for ClustersReader<D, P> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(Self::read(self, buf)?)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<D, P> std::io::Seek for ClustersReader<D, P> {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        Ok(Seek::seek(self, pos.into())?)
+    }
+
+    fn rewind(&mut self) -> std::io::Result<()> {
+        Self::rewind(self);
+        Ok(())
+    }
+
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        Ok(self.offset)
+    }
+}
+
+/// Returns the new position for [`Seek::seek()`] on the data with `len` bytes when the current
+/// position is `offset`.
+pub(crate) fn seek_offset(offset: u64, len: u64, pos: SeekFrom) -> Result<u64, SeekError> {
+    let o = match pos {
+        SeekFrom::Start(v) => v,
+        SeekFrom::End(v) => {
+            if v >= 0 {
+                len.saturating_add(v.unsigned_abs())
+            } else if let Some(v) = len.checked_sub(v.unsigned_abs()) {
+                v
+            } else {
+                return Err(SeekError::NegativeOffset);
+            }
+        }
+        SeekFrom::Current(v) => v.try_into().map_or_else(
+            |_| {
+                offset
+                    .checked_sub(v.unsigned_abs())
+                    .ok_or(SeekError::NegativeOffset)
+            },
+            |v| Ok(offset.saturating_add(v)),
+        )?,
+    };
+
+    Ok(o)
+}
+
+/// Clusters to read by [`ClustersReader`].
+#[derive(Clone)]
+enum Chain {
+    /// The clusters are contiguous so we don't need to store each of them.
+    Contiguous {
+        start: usize,
+        count: usize,
+    },
+    Fat(Vec<usize>),
+}
+
+impl Chain {
+    /// Gets the cluster at `index`. Panics if `index` is out of range.
+    fn get(&self, index: usize) -> usize {
+        match self {
+            Self::Contiguous { start, count } => {
+                assert!(index < *count);
+                start + index
+            }
+            Self::Fat(v) => v[index],
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Contiguous { count, .. } => *count,
+            Self::Fat(v) => v.len(),
+        }
+    }
+}
+
+/// A reader over the raw data of a cluster chain.
+pub struct RawReader<D> {
+    reader: ClustersReader<Arc<D>, Arc<Params>>,
+}
+
+impl<D> RawReader<D> {
+    pub(crate) fn new(reader: ClustersReader<Arc<D>, Arc<Params>>) -> Self {
+        Self { reader }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.reader.data_length()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reader.data_length() == 0
+    }
+
+    pub fn first_cluster(&self) -> usize {
+        self.reader.first_cluster()
+    }
+
+    /// Returns the number of clusters in the chain.
+    pub fn cluster_count(&self) -> usize {
+        self.reader.cluster_count()
+    }
+
+    /// Reads up to `clusters` contiguous clusters at once when the reads are sequential. See
+    /// [`File::with_readahead()`][crate::file::File::with_readahead()] for more details.
+    pub fn with_readahead(mut self, clusters: usize) -> Self {
+        self.reader.set_readahead(clusters);
+        self
+    }
+}
+
+impl<D: DiskPartition> RawReader<D> {
+    /// Same as [`Read::read()`] but the error from the partition is not boxed.
+    pub fn read_typed(&mut self, buf: &mut [u8]) -> Result<usize, TypedReadError<D::Err>> {
+        self.reader.read_typed(buf)
+    }
+}
+
+impl<D: DiskPartition> Read for RawReader<D> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ReadError> {
+        self.reader.read(buf)
+    }
+}
+
+impl<D> Seek for RawReader<D> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, SeekError> {
+        Seek::seek(&mut self.reader, pos)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<D: DiskPartition> std::io::Read for RawReader<D> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(self.reader.read(buf)?)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<D> std::io::Seek for RawReader<D> {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        Ok(Seek::seek(&mut self.reader, pos.into())?)
+    }
+}
+
+/// A LRU cache of cluster data.
+pub(crate) struct ClusterCache {
+    capacity: usize,
+    clusters: Mutex<VecDeque<(usize, Vec<u8>)>>, // Most recently used first.
+}
+
+impl ClusterCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            clusters: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Copies the data of `cluster` starting at `offset` into `buf`. Returns `false` if `cluster`
+    /// is not cached.
+    pub fn read(&self, cluster: usize, offset: usize, buf: &mut [u8]) -> bool {
+        let mut clusters = self.clusters.lock();
+        let i = match clusters.iter().position(|(c, _)| *c == cluster) {
+            Some(v) => v,
+            None => return false,
+        };
+
+        let entry = clusters.remove(i).unwrap();
+
+        buf.copy_from_slice(&entry.1[offset..(offset + buf.len())]);
+        clusters.push_front(entry);
+
+        true
+    }
+
+    pub fn insert(&self, cluster: usize, data: Vec<u8>) {
+        let mut clusters = self.clusters.lock();
+
+        if self.capacity == 0 {
+            return;
+        }
+
+        clusters.retain(|(c, _)| *c != cluster);
+        clusters.truncate(self.capacity - 1);
+        clusters.push_front((cluster, data));
+    }
+
+    /// Removes all cached clusters. This must be called after the partition has been written.
+    pub fn clear(&self) {
+        self.clusters.lock().clear();
+    }
 }
 
 /// Represents an error for [`new()`][ClustersReader::new()].
@@ -163,4 +580,7 @@ pub enum NewError {
 
     #[error("data length is not valid")]
     InvalidDataLength,
+
+    #[error("cluster #{0} has an invalid FAT entry")]
+    CorruptClusterChain(usize),
 }
@@ -0,0 +1,50 @@
+use core::hash::Hasher;
+
+/// CRC-32 (IEEE 802.3) lookup table.
+const TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+
+            k += 1;
+        }
+
+        table[i] = c;
+        i += 1;
+    }
+
+    table
+};
+
+/// Implementation of [`Hasher`] that computes CRC-32 (IEEE 802.3).
+pub(crate) struct Crc32 {
+    value: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Self { value: 0xffffffff }
+    }
+}
+
+impl Hasher for Crc32 {
+    fn finish(&self) -> u64 {
+        (self.value ^ 0xffffffff).into()
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.value = TABLE[((self.value ^ u32::from(b)) & 0xff) as usize] ^ (self.value >> 8);
+        }
+    }
+}
@@ -1,11 +1,22 @@
-use crate::cluster::ClustersReader;
-use crate::disk::DiskPartition;
-use crate::entries::{ClusterAllocation, EntriesReader, EntryType, FileEntry, StreamEntry};
+use crate::bitmap::{AllocError, Bitmap};
+use crate::cluster::{ClusterCache, ClustersReader, RawReader};
+use crate::disk::{DiskPartition, WritableDiskPartition};
+use crate::entries::{
+    ClusterAllocation, EntriesReader, EntryType, FileEntry, RawEntry, StreamEntry,
+};
 use crate::fat::Fat;
 use crate::file::File;
+use crate::options::OpenOptions;
 use crate::param::Params;
-use crate::timestamp::Timestamps;
+use crate::timestamp::{Timestamp, Timestamps};
+use crate::upcase::UpcaseTable;
+use crate::FileAttributes;
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use core::hash::{Hash, Hasher};
 use thiserror::Error;
 
 /// Represents a directory in an exFAT filesystem.
@@ -13,59 +24,171 @@ pub struct Directory<D> {
     disk: Arc<D>,
     params: Arc<Params>,
     fat: Arc<Fat>,
-    name: String,
-    stream: StreamEntry,
-    timestamps: Timestamps,
+    cache: Option<Arc<ClusterCache>>,
+    bitmap: Arc<Bitmap>,
+    upcase: Arc<UpcaseTable>,
+    options: Arc<OpenOptions>,
+    entry: FileEntry,
+    path: String,
+    parent: Option<Arc<Self>>,
 }
 
 impl<D> Directory<D> {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         disk: Arc<D>,
         params: Arc<Params>,
         fat: Arc<Fat>,
-        name: String,
-        stream: StreamEntry,
-        timestamps: Timestamps,
+        cache: Option<Arc<ClusterCache>>,
+        bitmap: Arc<Bitmap>,
+        upcase: Arc<UpcaseTable>,
+        options: Arc<OpenOptions>,
+        entry: FileEntry,
     ) -> Self {
         Self {
             disk,
             params,
             fat,
-            name,
-            stream,
-            timestamps,
+            cache,
+            bitmap,
+            upcase,
+            options,
+            entry,
+            path: String::from("/"),
+            parent: None,
         }
     }
 
+    /// Creates a [`Directory`] for a child directory of this directory.
+    pub(crate) fn child(&self, entry: FileEntry) -> Self {
+        let mut path = self.path.clone();
+
+        if !self.is_root() {
+            path.push('/');
+        }
+
+        path.push_str(&entry.name);
+
+        Self {
+            path,
+            parent: Some(Arc::new(self.clone())),
+            ..Self::new(
+                self.disk.clone(),
+                self.params.clone(),
+                self.fat.clone(),
+                self.cache.clone(),
+                self.bitmap.clone(),
+                self.upcase.clone(),
+                self.options.clone(),
+                entry,
+            )
+        }
+    }
+
+    /// Creates a [`File`] for a file in this directory.
+    pub(crate) fn new_file(&self, entry: FileEntry) -> Result<File<D>, crate::file::NewError> {
+        File::new(
+            &self.disk,
+            &self.params,
+            &self.fat,
+            &self.cache,
+            &self.bitmap,
+            entry,
+        )
+    }
+
     pub fn name(&self) -> &str {
-        self.name.as_ref()
+        self.entry.name.as_ref()
+    }
+
+    /// Returns the name as UTF-16 code units stored on the volume. See [`File::name_utf16()`] for
+    /// more details.
+    pub fn name_utf16(&self) -> &[u16] {
+        &self.entry.name_utf16
+    }
+
+    /// Returns the absolute path of this directory (e.g. `/dir1/sub`) at the time it was opened.
+    ///
+    /// The path will not be updated if this directory or its parents are renamed.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn attributes(&self) -> FileAttributes {
+        self.entry.attributes
     }
 
     pub fn timestamps(&self) -> &Timestamps {
-        &self.timestamps
+        &self.entry.timestamps
+    }
+
+    /// Returns the cluster and the index of the File Directory Entry for this directory.
+    pub fn entry_location(&self) -> (usize, usize) {
+        (self.entry.cluster, self.entry.index)
+    }
+
+    /// Returns SecondaryCount of the File Directory Entry for this directory.
+    pub fn secondary_count(&self) -> usize {
+        self.entry.secondary_count()
+    }
+
+    pub fn metadata(&self) -> Metadata {
+        Metadata::new(&self.entry)
+    }
+
+    /// Returns the Stream Extension Directory Entry of this directory. The entry of the root
+    /// directory is synthesized from the Main Boot Sector.
+    pub fn stream_entry(&self) -> &StreamEntry {
+        &self.entry.stream
+    }
+
+    /// Returns `true` if NoFatChain flag is set, which means the clusters are contiguous. This is always
+    /// `false` for the root directory.
+    pub fn is_no_fat_chain(&self) -> bool {
+        self.entry.stream.no_fat_chain()
+    }
+
+    pub(crate) fn upcase(&self) -> &UpcaseTable {
+        &self.upcase
+    }
+
+    pub(crate) fn bitmap(&self) -> &Bitmap {
+        &self.bitmap
+    }
+
+    /// Creates a copy of this [`Directory`] with a different name.
+    fn renamed(&self, name: &str) -> Self {
+        let mut dir = self.clone();
+
+        dir.entry.name = String::from(name);
+        dir.entry.name_utf16 = name.encode_utf16().collect();
+        dir
+    }
+
+    /// Returns `true` if this is the root directory.
+    fn is_root(&self) -> bool {
+        self.entry.entries.is_empty()
     }
 }
 
 impl<D: DiskPartition> Directory<D> {
     pub fn open(&self) -> Result<Vec<Item<D>>, DirectoryError> {
         // Create an entries reader.
-        let alloc = self.stream.allocation();
-        let mut reader = match ClustersReader::new(
-            &self.disk,
-            &self.params,
-            &self.fat,
-            alloc.first_cluster(),
-            Some(alloc.data_length()),
-            Some(self.stream.no_fat_chain()),
-        ) {
-            Ok(v) => EntriesReader::new(v),
-            Err(e) => return Err(DirectoryError::CreateClustersReaderFailed(alloc.clone(), e)),
+        let mut reader = match self.entries_reader() {
+            Ok(Some(v)) => v,
+            Ok(None) => return Ok(Vec::new()),
+            Err(e) => {
+                let alloc = self.entry.stream.allocation().clone();
+                return Err(DirectoryError::CreateClustersReaderFailed(alloc, e));
+            }
         };
 
+        reader.set_skip_deleted(self.options.skip_deleted_entries);
+
         // Read file entries.
         let mut items: Vec<Item<D>> = Vec::new();
 
-        loop {
+        while !reader.is_end() {
             // Read primary entry.
             let entry = match reader.read() {
                 Ok(v) => v,
@@ -75,7 +198,9 @@ impl<D: DiskPartition> Directory<D> {
             // Check entry type.
             let ty = entry.ty();
 
-            if !ty.is_regular() {
+            if self.is_root() && ty.is_empty_volume_label() {
+                continue;
+            } else if !ty.is_regular() {
                 break;
             } else if ty.type_category() != EntryType::PRIMARY {
                 return Err(DirectoryError::NotPrimaryEntry(
@@ -83,39 +208,42 @@ impl<D: DiskPartition> Directory<D> {
                     entry.cluster(),
                 ));
             } else if ty.type_importance() != EntryType::CRITICAL || ty.type_code() != 5 {
-                return Err(DirectoryError::NotFileEntry(entry.index(), entry.cluster()));
+                // The root directory also contains Allocation Bitmap, Up-case Table and Volume
+                // Label, which have no secondary entries. Unknown benign entries can be safely
+                // skipped.
+                if self.is_root() {
+                    if ty.type_importance() == EntryType::CRITICAL
+                        && (1..=3).contains(&ty.type_code())
+                    {
+                        continue;
+                    }
+                } else if ty.type_importance() == EntryType::CRITICAL
+                    && !self.options.ignore_unknown_entries
+                {
+                    return Err(DirectoryError::NotFileEntry(entry.index(), entry.cluster()));
+                }
+
+                // Skip secondary entries.
+                for _ in 0..entry.data()[1] {
+                    if let Err(e) = reader.read() {
+                        return Err(DirectoryError::ReadEntryFailed(e));
+                    }
+                }
+
+                continue;
             }
 
             // Parse file entry.
-            let file = match FileEntry::load(&entry, &mut reader) {
+            let file = match FileEntry::load(&entry, &mut reader, &self.upcase, &self.options) {
                 Ok(v) => v,
                 Err(e) => return Err(DirectoryError::LoadFileEntryFailed(e)),
             };
 
             // Construct item.
-            let name = file.name;
-            let attrs = file.attributes;
-            let stream = file.stream;
-            let timestamps = file.timestamps;
-
-            items.push(if attrs.is_directory() {
-                Item::Directory(Self {
-                    disk: self.disk.clone(),
-                    params: self.params.clone(),
-                    fat: self.fat.clone(),
-                    name,
-                    stream,
-                    timestamps,
-                })
+            items.push(if file.attributes.is_directory() {
+                Item::Directory(self.child(file))
             } else {
-                match File::new(
-                    &self.disk,
-                    &self.params,
-                    &self.fat,
-                    name,
-                    stream,
-                    timestamps,
-                ) {
+                match self.new_file(file) {
                     Ok(v) => Item::File(v),
                     Err(e) => {
                         return Err(DirectoryError::CreateFileObjectFailed(
@@ -130,6 +258,708 @@ impl<D: DiskPartition> Directory<D> {
 
         Ok(items)
     }
+
+    /// Same as [`Directory::open()`] but with `.` and `..` at the beginning like a FAT32
+    /// directory.
+    ///
+    /// exFAT directories do not contain these entries so they are synthesized: `.` refers to this
+    /// directory and `..` refers to the parent directory, or this directory for the root directory.
+    /// Other than the name they are the same as the directory they refer to, including
+    /// [`Directory::entry_location()`].
+    pub fn open_with_dots(&self) -> Result<Vec<Item<D>>, DirectoryError> {
+        let parent = self.parent.as_deref().unwrap_or(self);
+        let mut items = vec![
+            Item::Directory(self.renamed(".")),
+            Item::Directory(parent.renamed("..")),
+        ];
+
+        items.extend(self.open()?);
+
+        Ok(items)
+    }
+
+    /// Same as [`Directory::open()`] but the items are sorted by their up-cased name.
+    ///
+    /// The names are compared by UTF-16 code units after up-casing with the Up-case Table of the
+    /// volume, which is the same order as Windows.
+    pub fn open_sorted(&self) -> Result<Vec<Item<D>>, DirectoryError> {
+        let mut items = self.open()?;
+
+        items.sort_by_cached_key(|i| {
+            i.name()
+                .encode_utf16()
+                .map(|c| self.upcase.map(c))
+                .collect::<Vec<u16>>()
+        });
+
+        Ok(items)
+    }
+
+    /// Finds an item named `name` in this directory. The name is case-insensitive.
+    ///
+    /// This stops reading the entries as soon as the item is found, which is faster than searching
+    /// the result of [`Directory::open()`].
+    pub fn find(&self, name: &str) -> Result<Option<Item<D>>, DirectoryError> {
+        let file = match self.scan(name, 0, &[])? {
+            (Some(v), _) => v,
+            (None, _) => return Ok(None),
+        };
+
+        if file.attributes.is_directory() {
+            return Ok(Some(Item::Directory(self.child(file))));
+        }
+
+        let (cluster, index) = (file.cluster, file.index);
+
+        match self.new_file(file) {
+            Ok(v) => Ok(Some(Item::File(v))),
+            Err(e) => Err(DirectoryError::CreateFileObjectFailed(index, cluster, e)),
+        }
+    }
+
+    /// Marks the clusters of all files and directories under this directory in `used`, which is a
+    /// bitset of the clusters in the Cluster Heap. Returns the first cluster that was already
+    /// marked.
+    pub(crate) fn mark_clusters(&self, used: &mut [u8]) -> Result<Option<usize>, DirectoryError> {
+        for item in self.open()? {
+            // The chain of a directory is not validated until it is opened.
+            let clusters = match &item {
+                Item::Directory(d) => d.entry.stream.clusters(&self.params, &self.fat),
+                Item::File(f) => f.clusters(),
+            };
+
+            let clusters = match clusters {
+                Ok(v) => v,
+                Err(e) => return Err(DirectoryError::CorruptClusterChain(e)),
+            };
+
+            if let Some(c) = mark_clusters(used, clusters) {
+                return Ok(Some(c));
+            }
+
+            if let Item::Directory(d) = &item {
+                if let Some(c) = d.mark_clusters(used)? {
+                    return Ok(Some(c));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the number of files and directories in this directory.
+    ///
+    /// This only counts File entries without loading their entry sets so it is cheaper than
+    /// [`Directory::open()`]. No validation is performed on the entry sets.
+    pub fn entry_count(&self) -> Result<usize, DirectoryError> {
+        // Create an entries reader.
+        let mut reader = match self.entries_reader() {
+            Ok(Some(v)) => v,
+            Ok(None) => return Ok(0),
+            Err(e) => {
+                let alloc = self.entry.stream.allocation().clone();
+                return Err(DirectoryError::CreateClustersReaderFailed(alloc, e));
+            }
+        };
+
+        reader.set_skip_deleted(self.options.skip_deleted_entries);
+
+        // Count File entries.
+        let mut count = 0;
+
+        while !reader.is_end() {
+            let entry = match reader.read() {
+                Ok(v) => v,
+                Err(e) => return Err(DirectoryError::ReadEntryFailed(e)),
+            };
+
+            let ty = entry.ty();
+
+            if self.is_root() && ty.is_empty_volume_label() {
+                continue;
+            } else if !ty.is_regular() {
+                break;
+            } else if ty.type_importance() == EntryType::CRITICAL
+                && ty.type_category() == EntryType::PRIMARY
+                && ty.type_code() == 5
+            {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Returns the number of directory entries that can be used without growing this directory.
+    ///
+    /// This counts all entries that are not in use, including the deleted entries and the entries
+    /// after the end of directory. A file requires at least 3 entries.
+    pub fn free_slots(&self) -> Result<usize, DirectoryError> {
+        // Create an entries reader.
+        let mut reader = match self.entries_reader() {
+            Ok(Some(v)) => v,
+            Ok(None) => return Ok(0),
+            Err(e) => {
+                let alloc = self.entry.stream.allocation().clone();
+                return Err(DirectoryError::CreateClustersReaderFailed(alloc, e));
+            }
+        };
+
+        // Count unused entries.
+        let mut count = 0;
+
+        while !reader.is_end() {
+            let entry = match reader.read() {
+                Ok(v) => v,
+                Err(e) => return Err(DirectoryError::ReadEntryFailed(e)),
+            };
+
+            if !entry.ty().is_in_use() {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Returns an iterator over all entries in this directory, including the unused entries.
+    ///
+    /// The iteration will not stop at the end of directory entry.
+    pub fn raw_entries(&self) -> Result<RawEntries<'_, D>, DirectoryError> {
+        match self.entries_reader() {
+            Ok(reader) => Ok(RawEntries { reader }),
+            Err(e) => {
+                let alloc = self.entry.stream.allocation().clone();
+                Err(DirectoryError::CreateClustersReaderFailed(alloc, e))
+            }
+        }
+    }
+
+    /// Returns a reader over the raw data of this directory. Returns [`None`] if the directory has
+    /// no clusters.
+    pub fn raw_reader(&self) -> Result<Option<RawReader<D>>, DirectoryError> {
+        let stream = &self.entry.stream;
+        let alloc = stream.allocation();
+
+        if alloc.first_cluster() == 0 {
+            return Ok(None);
+        }
+
+        let mut reader = match ClustersReader::new(
+            self.disk.clone(),
+            self.params.clone(),
+            &self.fat,
+            alloc.first_cluster(),
+            Some(alloc.data_length()),
+            Some(stream.no_fat_chain()),
+        ) {
+            Ok(v) => v,
+            Err(e) => return Err(DirectoryError::CreateClustersReaderFailed(alloc.clone(), e)),
+        };
+
+        reader.set_cache(self.cache.clone());
+
+        Ok(Some(RawReader::new(reader)))
+    }
+
+    /// Returns the deleted files in this directory that still have an intact entry set.
+    ///
+    /// There is no guarantee that the clusters of the returned files are not reused by other
+    /// files.
+    pub fn deleted_entries(&self) -> Result<Vec<DeletedFile>, DirectoryError> {
+        // Read all entries.
+        let mut entries = Vec::new();
+
+        for entry in self.raw_entries()? {
+            match entry {
+                Ok(v) => entries.push(v),
+                Err(e) => return Err(DirectoryError::ReadEntryFailed(e)),
+            }
+        }
+
+        // Find deleted File entries.
+        let mut files = Vec::new();
+        let mut i = 0;
+
+        while i < entries.len() {
+            let data = entries[i].data();
+
+            if data[0] != 0x05 {
+                i += 1;
+                continue;
+            }
+
+            // Try to load the entry set.
+            let end = i + 1 + data[1] as usize;
+
+            match entries
+                .get(i..end)
+                .and_then(|set| FileEntry::recover(set, &self.upcase, self.options.skip_checksums))
+            {
+                Some(entry) => {
+                    files.push(DeletedFile { entry });
+                    i = end;
+                }
+                None => i += 1,
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Finds the entry set for `name` and the first run of `count` free entries.
+    ///
+    /// The entries in `reuse` are considered free. The returned run may be shorter than `count` if
+    /// it is at the end of the directory. The search stops as soon as `name` is found.
+    ///
+    /// If `count` is zero only the items that [`Directory::open()`] would return can be found;
+    /// otherwise all entries are checked so a name that is hidden by a deleted entry is not created
+    /// twice.
+    pub(crate) fn scan(
+        &self,
+        name: &str,
+        count: usize,
+        reuse: &[RawEntry],
+    ) -> Result<(Option<FileEntry>, Vec<RawEntry>), DirectoryError> {
+        // Create an entries reader.
+        let mut reader = match self.entries_reader() {
+            Ok(Some(v)) => v,
+            Ok(None) => return Ok((None, Vec::new())),
+            Err(e) => {
+                let alloc = self.entry.stream.allocation().clone();
+                return Err(DirectoryError::CreateClustersReaderFailed(alloc, e));
+            }
+        };
+
+        // Deleted entries can be reused.
+        let mut slots = Vec::with_capacity(count);
+
+        while !reader.is_end() {
+            let entry = match reader.read() {
+                Ok(v) => v,
+                Err(e) => return Err(DirectoryError::ReadEntryFailed(e)),
+            };
+
+            let ty = entry.ty();
+
+            // Stop where Directory::open() would stop.
+            if count == 0 && !ty.is_in_use() {
+                let label = self.is_root() && ty.is_empty_volume_label();
+
+                if !(label || self.options.skip_deleted_entries) {
+                    break;
+                }
+            }
+
+            let reusable = reuse
+                .iter()
+                .any(|r| r.cluster() == entry.cluster() && r.index() == entry.index());
+
+            if !ty.is_in_use() || reusable {
+                let end = ty.is_end_of_directory();
+
+                if slots.len() < count {
+                    slots.push(entry);
+                }
+
+                // No more entries after the end of directory.
+                if end && slots.len() == count {
+                    break;
+                }
+
+                continue;
+            }
+
+            if slots.len() < count {
+                slots.clear();
+            }
+
+            // Check if the name already exists.
+            if ty.type_importance() != EntryType::CRITICAL
+                || ty.type_category() != EntryType::PRIMARY
+                || ty.type_code() != 5
+            {
+                continue;
+            }
+
+            let file = match FileEntry::load(&entry, &mut reader, &self.upcase, &self.options) {
+                Ok(v) => v,
+                Err(e) => return Err(DirectoryError::LoadFileEntryFailed(e)),
+            };
+
+            if self.upcase.eq_ignore_case(&file.name, name) {
+                return Ok((Some(file), slots));
+            }
+        }
+
+        Ok((None, slots))
+    }
+
+    /// Creates an [`EntriesReader`] for this directory. Returns [`None`] if the directory has no
+    /// clusters.
+    #[allow(clippy::type_complexity)]
+    fn entries_reader(
+        &self,
+    ) -> Result<Option<EntriesReader<&Arc<D>, &Arc<Params>>>, crate::cluster::NewError> {
+        let stream = &self.entry.stream;
+        let alloc = stream.allocation();
+
+        if alloc.first_cluster() == 0 {
+            return Ok(None);
+        }
+
+        let mut reader = ClustersReader::new(
+            &self.disk,
+            &self.params,
+            &self.fat,
+            alloc.first_cluster(),
+            Some(alloc.data_length()),
+            Some(stream.no_fat_chain()),
+        )?;
+
+        reader.set_cache(self.cache.clone());
+
+        Ok(Some(EntriesReader::new(reader)))
+    }
+}
+
+impl<D: WritableDiskPartition> Directory<D> {
+    /// Creates an empty file named `name` in this directory.
+    ///
+    /// The directory will be extended by one or more clusters if there are not enough free
+    /// entries. The timestamps will be 1980-01-01 00:00:00 if the `std` feature is disabled.
+    pub fn create_file(&mut self, name: &str) -> Result<File<D>, CreateError<D>> {
+        let slots = self.alloc_entries(name)?;
+
+        // Write the entry set.
+        let now = Timestamp::now_or_min();
+        let timestamps = Timestamps::new(now, now, now);
+        let entry = FileEntry::new(
+            slots,
+            name,
+            FileAttributes::from_bits(FileAttributes::ARCHIVE),
+            timestamps,
+            &self.upcase,
+        );
+
+        self.write_entries(&entry.entries)?;
+
+        match self.new_file(entry) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(CreateError::CreateFileObjectFailed(e)),
+        }
+    }
+
+    /// Creates an empty directory named `name` in this directory.
+    ///
+    /// The new directory will have one cluster filled with zeros. This directory will be extended
+    /// the same way as [`Directory::create_file()`].
+    pub fn create_dir(&mut self, name: &str) -> Result<Directory<D>, CreateError<D>> {
+        let slots = self.alloc_entries(name)?;
+
+        // Allocate a cluster for the new directory.
+        let disk = self.disk.as_ref();
+        let cluster = match self.bitmap.alloc_contiguous(disk, 1) {
+            Ok(Some(v)) => v,
+            Ok(None) => return Err(CreateError::AllocateFailed(AllocError::NoSpace)),
+            Err(e) => {
+                return Err(CreateError::AllocateFailed(AllocError::UpdateBitmapFailed(
+                    e,
+                )))
+            }
+        };
+
+        // Clear the cluster so it contains only end of directory entries.
+        let cluster_size = self.params.cluster_size();
+        let offset = match self.params.cluster_offset(cluster) {
+            Some(v) => v,
+            None => return Err(CreateError::InvalidEntryLocation),
+        };
+
+        if let Some(c) = &self.cache {
+            c.clear();
+        }
+
+        if let Err(e) = disk.write_all(offset, &vec![0u8; cluster_size as usize]) {
+            return Err(CreateError::WriteFailed(offset, e));
+        }
+
+        // Write the entry set.
+        let now = Timestamp::now_or_min();
+        let timestamps = Timestamps::new(now, now, now);
+        let mut entry = FileEntry::new(
+            slots,
+            name,
+            FileAttributes::from_bits(FileAttributes::DIRECTORY),
+            timestamps,
+            &self.upcase,
+        );
+
+        entry.set_allocation(cluster, true, cluster_size, cluster_size);
+
+        self.write_entries(&entry.entries)?;
+
+        Ok(self.child(entry))
+    }
+
+    /// Validates `name` and finds free entries for its entry set, extending this directory if
+    /// necessary.
+    fn alloc_entries(&mut self, name: &str) -> Result<Vec<RawEntry>, CreateError<D>> {
+        // Check name.
+        let len = match name_length(name) {
+            Some(v) => v,
+            None => return Err(CreateError::InvalidName),
+        };
+
+        // Find free entries and check if the name already exists.
+        let count = 2 + len.div_ceil(15);
+        let mut slots = match self.scan(name, count, &[]) {
+            Ok((Some(_), _)) => return Err(CreateError::AlreadyExists),
+            Ok((None, v)) => v,
+            Err(e) => return Err(CreateError::ReadDirectoryFailed(e)),
+        };
+
+        if slots.len() < count {
+            self.extend(&mut slots, count)?;
+        }
+
+        Ok(slots)
+    }
+
+    /// Allocates new clusters for this directory until `slots` has `count` entries.
+    pub(crate) fn extend(
+        &mut self,
+        slots: &mut Vec<RawEntry>,
+        count: usize,
+    ) -> Result<(), CreateError<D>> {
+        // Allocate clusters.
+        let disk = self.disk.as_ref();
+        let cluster_size = self.params.cluster_size();
+        let need = ((count - slots.len()) as u64 * 32).div_ceil(cluster_size) as usize;
+        let stream = &self.entry.stream;
+        let mut chain = match stream.clusters(&self.params, &self.fat) {
+            Ok(v) => v,
+            Err(e) => return Err(CreateError::CorruptClusterChain(e)),
+        };
+
+        let start = chain.len();
+        let no_fat_chain = match self.bitmap.grow(
+            disk,
+            &self.fat,
+            &mut chain,
+            stream.no_fat_chain(),
+            start + need,
+        ) {
+            Ok(v) => v,
+            Err(e) => return Err(CreateError::AllocateFailed(e)),
+        };
+
+        // Clear the new clusters so they contain only end of directory entries.
+        let zeros = vec![0u8; cluster_size as usize];
+
+        if let Some(c) = &self.cache {
+            c.clear();
+        }
+
+        for &cluster in &chain[start..] {
+            let offset = match self.params.cluster_offset(cluster) {
+                Some(v) => v,
+                None => return Err(CreateError::InvalidEntryLocation),
+            };
+
+            if let Err(e) = disk.write_all(offset, &zeros) {
+                return Err(CreateError::WriteFailed(offset, e));
+            }
+
+            for index in 0..(cluster_size / 32) as usize {
+                if slots.len() < count {
+                    slots.push(RawEntry::new(index, cluster));
+                }
+            }
+        }
+
+        // Update the entry set of this directory.
+        let len = chain.len() as u64 * cluster_size;
+
+        self.entry.set_allocation(chain[0], no_fat_chain, len, len);
+
+        let entries = self.entry.entries.get(..2).unwrap_or_default();
+
+        self.write_entries(entries)
+    }
+
+    /// Writes `entries` to the partition.
+    pub(crate) fn write_entries(&self, entries: &[RawEntry]) -> Result<(), CreateError<D>> {
+        if let Some(c) = &self.cache {
+            c.clear();
+        }
+
+        for entry in entries {
+            let offset = match entry.offset(&self.params) {
+                Some(v) => v,
+                None => return Err(CreateError::InvalidEntryLocation),
+            };
+
+            if let Err(e) = self.disk.write_all(offset, entry.data()) {
+                return Err(CreateError::WriteFailed(offset, e));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the number of UTF-16 code units in `name` if it is a valid file name.
+pub(crate) fn name_length(name: &str) -> Option<usize> {
+    let len = name.encode_utf16().count();
+
+    if len == 0 || len > 255 || name == "." || name == ".." {
+        return None;
+    }
+
+    if name
+        .chars()
+        .any(|c| c < ' ' || matches!(c, '"' | '*' | '/' | ':' | '<' | '>' | '?' | '\\' | '|'))
+    {
+        return None;
+    }
+
+    Some(len)
+}
+
+/// Marks `clusters` in `used`. Returns the first cluster that was already marked.
+pub(crate) fn mark_clusters(
+    used: &mut [u8],
+    clusters: impl IntoIterator<Item = usize>,
+) -> Option<usize> {
+    for cluster in clusters {
+        let i = match cluster.checked_sub(2) {
+            Some(v) if v / 8 < used.len() => v,
+            _ => continue,
+        };
+
+        let bit = 1 << (i % 8);
+
+        if used[i / 8] & bit != 0 {
+            return Some(cluster);
+        }
+
+        used[i / 8] |= bit;
+    }
+
+    None
+}
+
+/// Two directories are equal if they have the same [`Directory::entry_location()`]. This is only
+/// meaningful for directories on the same volume.
+impl<D> PartialEq for Directory<D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entry_location() == other.entry_location()
+    }
+}
+
+impl<D> Eq for Directory<D> {}
+
+impl<D> Hash for Directory<D> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.entry_location().hash(state);
+    }
+}
+
+impl<D> Clone for Directory<D> {
+    fn clone(&self) -> Self {
+        Self {
+            disk: self.disk.clone(),
+            params: self.params.clone(),
+            fat: self.fat.clone(),
+            cache: self.cache.clone(),
+            bitmap: self.bitmap.clone(),
+            upcase: self.upcase.clone(),
+            options: self.options.clone(),
+            entry: self.entry.clone(),
+            path: self.path.clone(),
+            parent: self.parent.clone(),
+        }
+    }
+}
+
+/// Represents a deleted file that was found by [`Directory::deleted_entries()`].
+pub struct DeletedFile {
+    entry: FileEntry,
+}
+
+impl DeletedFile {
+    pub fn name(&self) -> &str {
+        self.entry.name.as_ref()
+    }
+
+    /// Returns the name as UTF-16 code units stored on the volume. See [`File::name_utf16()`] for
+    /// more details.
+    pub fn name_utf16(&self) -> &[u16] {
+        &self.entry.name_utf16
+    }
+
+    pub fn attributes(&self) -> FileAttributes {
+        self.entry.attributes
+    }
+
+    pub fn timestamps(&self) -> &Timestamps {
+        &self.entry.timestamps
+    }
+
+    /// Returns the cluster and the index of the File Directory Entry for this file.
+    pub fn entry_location(&self) -> (usize, usize) {
+        (self.entry.cluster, self.entry.index)
+    }
+
+    /// Returns the first cluster of the data or zero if the file is empty.
+    pub fn first_cluster(&self) -> usize {
+        self.entry.stream.allocation().first_cluster()
+    }
+
+    pub fn stream_entry(&self) -> &StreamEntry {
+        &self.entry.stream
+    }
+
+    /// Returns `true` if the clusters of the data are contiguous.
+    pub fn no_fat_chain(&self) -> bool {
+        self.entry.stream.no_fat_chain()
+    }
+
+    /// Returns ValidDataLength of the file.
+    pub fn len(&self) -> u64 {
+        self.entry.stream.valid_data_length()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns DataLength of the file.
+    pub fn allocated_len(&self) -> u64 {
+        self.entry.stream.allocation().data_length()
+    }
+}
+
+/// An iterator over all entries in a directory.
+///
+/// This struct is created by [`Directory::raw_entries()`].
+pub struct RawEntries<'a, D> {
+    reader: Option<EntriesReader<&'a Arc<D>, &'a Arc<Params>>>,
+}
+
+impl<D: DiskPartition> Iterator for RawEntries<'_, D> {
+    type Item = Result<RawEntry, crate::entries::ReaderError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let reader = self.reader.as_mut()?;
+
+        if reader.is_end() {
+            None
+        } else {
+            Some(reader.read())
+        }
+    }
 }
 
 /// Represents an item in the directory.
@@ -138,6 +968,128 @@ pub enum Item<D> {
     File(File<D>),
 }
 
+impl<D> Item<D> {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Directory(d) => d.name(),
+            Self::File(f) => f.name(),
+        }
+    }
+
+    /// Returns the name as UTF-16 code units stored on the volume. See [`File::name_utf16()`] for
+    /// more details.
+    pub fn name_utf16(&self) -> &[u16] {
+        match self {
+            Self::Directory(d) => d.name_utf16(),
+            Self::File(f) => f.name_utf16(),
+        }
+    }
+
+    pub fn is_directory(&self) -> bool {
+        matches!(self, Self::Directory(_))
+    }
+
+    pub fn as_directory(&self) -> Option<&Directory<D>> {
+        match self {
+            Self::Directory(v) => Some(v),
+            Self::File(_) => None,
+        }
+    }
+
+    pub fn as_file(&self) -> Option<&File<D>> {
+        match self {
+            Self::Directory(_) => None,
+            Self::File(v) => Some(v),
+        }
+    }
+
+    /// Returns the cluster and the index of the File Directory Entry for this item.
+    ///
+    /// The returned value is unique within the volume so it can be used as a stable identifier
+    /// (e.g. an inode number).
+    pub fn entry_location(&self) -> (usize, usize) {
+        match self {
+            Self::Directory(d) => d.entry_location(),
+            Self::File(f) => f.entry_location(),
+        }
+    }
+
+    /// Returns the index of the File Directory Entry and the number of entries in the entry set.
+    pub fn entry_set(&self) -> (usize, usize) {
+        let (_, index) = self.entry_location();
+        let count = match self {
+            Self::Directory(d) => d.secondary_count(),
+            Self::File(f) => f.secondary_count(),
+        };
+
+        (index, 1 + count)
+    }
+
+    pub fn metadata(&self) -> Metadata {
+        match self {
+            Self::Directory(d) => d.metadata(),
+            Self::File(f) => f.metadata(),
+        }
+    }
+
+    pub fn stream_entry(&self) -> &StreamEntry {
+        match self {
+            Self::Directory(d) => d.stream_entry(),
+            Self::File(f) => f.stream_entry(),
+        }
+    }
+}
+
+/// Two items are equal if they have the same [`Item::entry_location()`]. This is only meaningful
+/// for items on the same volume.
+impl<D> PartialEq for Item<D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entry_location() == other.entry_location()
+    }
+}
+
+impl<D> Eq for Item<D> {}
+
+impl<D> Hash for Item<D> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.entry_location().hash(state);
+    }
+}
+
+/// A snapshot of the information of a file or directory.
+#[derive(Clone)]
+pub struct Metadata {
+    pub name: String,
+    /// ValidDataLength of the item.
+    pub len: u64,
+    /// DataLength of the item.
+    pub allocated_len: u64,
+    pub attributes: FileAttributes,
+    pub timestamps: Timestamps,
+    /// Zero if no clusters was allocated.
+    pub first_cluster: usize,
+    pub is_directory: bool,
+    /// Value of NoFatChain flag.
+    pub is_contiguous: bool,
+}
+
+impl Metadata {
+    pub(crate) fn new(entry: &FileEntry) -> Self {
+        let stream = &entry.stream;
+
+        Self {
+            name: entry.name.clone(),
+            len: stream.valid_data_length(),
+            allocated_len: stream.allocation().data_length(),
+            attributes: entry.attributes,
+            timestamps: entry.timestamps,
+            first_cluster: stream.allocation().first_cluster(),
+            is_directory: entry.attributes.is_directory(),
+            is_contiguous: stream.no_fat_chain(),
+        }
+    }
+}
+
 /// Represents an error when [`Directory::open()`] fails.
 #[derive(Debug, Error)]
 pub enum DirectoryError {
@@ -158,4 +1110,60 @@ pub enum DirectoryError {
 
     #[error("cannot create a file object for directory entry #{0} on cluster #{1}")]
     CreateFileObjectFailed(usize, usize, #[source] crate::file::NewError),
+
+    #[error("cluster #{0} has an invalid FAT entry")]
+    CorruptClusterChain(usize),
+}
+
+/// Represents an error when [`Directory::create_file()`] fails.
+#[derive(Error)]
+pub enum CreateError<D: DiskPartition> {
+    #[error("the name is not valid")]
+    InvalidName,
+
+    #[error("the name already exists")]
+    AlreadyExists,
+
+    #[error("cannot read the directory")]
+    ReadDirectoryFailed(#[source] DirectoryError),
+
+    #[error("cannot allocate a cluster for the directory")]
+    AllocateFailed(#[source] AllocError<D>),
+
+    #[error("location of the entry is not valid")]
+    InvalidEntryLocation,
+
+    #[error("cannot write the data at {0:#x}")]
+    WriteFailed(u64, #[source] D::Err),
+
+    #[error("cannot create a file object")]
+    CreateFileObjectFailed(#[source] crate::file::NewError),
+
+    #[error("cluster #{0} has an invalid FAT entry")]
+    CorruptClusterChain(usize),
+}
+
+impl<D: DiskPartition> Debug for CreateError<D> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidName => write!(f, "InvalidName"),
+            Self::AlreadyExists => write!(f, "AlreadyExists"),
+            Self::ReadDirectoryFailed(arg0) => {
+                f.debug_tuple("ReadDirectoryFailed").field(arg0).finish()
+            }
+            Self::AllocateFailed(arg0) => f.debug_tuple("AllocateFailed").field(arg0).finish(),
+            Self::InvalidEntryLocation => write!(f, "InvalidEntryLocation"),
+            Self::WriteFailed(arg0, arg1) => f
+                .debug_tuple("WriteFailed")
+                .field(arg0)
+                .field(arg1)
+                .finish(),
+            Self::CreateFileObjectFailed(arg0) => {
+                f.debug_tuple("CreateFileObjectFailed").field(arg0).finish()
+            }
+            Self::CorruptClusterChain(arg0) => {
+                f.debug_tuple("CorruptClusterChain").field(arg0).finish()
+            }
+        }
+    }
 }
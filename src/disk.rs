@@ -1,13 +1,24 @@
+use alloc::boxed::Box;
 use alloc::sync::Arc;
 use core::error::Error;
+use core::fmt::{Display, Formatter};
 use core::ops::Deref;
 
 /// Encapsulate a disk partition.
+#[allow(clippy::len_without_is_empty)]
 pub trait DiskPartition {
     type Err: PartitionError + 'static;
 
     fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Err>;
 
+    /// Returns the size of this partition in bytes or [`None`] if it is not known.
+    ///
+    /// This is used to detect a truncated partition before reading it. The default implementation
+    /// returns [`None`].
+    fn len(&self) -> Option<u64> {
+        None
+    }
+
     fn read_exact(&self, mut offset: u64, mut buf: &mut [u8]) -> Result<(), Self::Err> {
         while !buf.is_empty() {
             let n = self.read(offset, buf)?;
@@ -29,27 +40,208 @@ pub trait DiskPartition {
     }
 }
 
+/// Encapsulate a disk partition that can be written.
+pub trait WritableDiskPartition: DiskPartition {
+    fn write(&self, offset: u64, buf: &[u8]) -> Result<usize, Self::Err>;
+
+    fn write_all(&self, mut offset: u64, mut buf: &[u8]) -> Result<(), Self::Err> {
+        while !buf.is_empty() {
+            let n = self.write(offset, buf)?;
+
+            if n == 0 {
+                return Err(PartitionError::unexpected_eop());
+            }
+
+            offset = n
+                .try_into()
+                .ok()
+                .and_then(|n| offset.checked_add(n))
+                .unwrap();
+
+            buf = &buf[n..];
+        }
+
+        Ok(())
+    }
+}
+
 /// Represents an error when an operation on [`DiskPartition`] fails.
 pub trait PartitionError: Error + Send + Sync {
     fn unexpected_eop() -> Self;
 }
 
-impl<T: DiskPartition> DiskPartition for &T {
+impl<T: DiskPartition + ?Sized> DiskPartition for &T {
     type Err = T::Err;
 
     fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Err> {
         (*self).read(offset, buf)
     }
+
+    fn len(&self) -> Option<u64> {
+        (*self).len()
+    }
+}
+
+impl<T: WritableDiskPartition + ?Sized> WritableDiskPartition for &T {
+    fn write(&self, offset: u64, buf: &[u8]) -> Result<usize, Self::Err> {
+        (*self).write(offset, buf)
+    }
+}
+
+impl<T: DiskPartition + ?Sized> DiskPartition for Box<T> {
+    type Err = T::Err;
+
+    fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Err> {
+        self.deref().read(offset, buf)
+    }
+
+    fn len(&self) -> Option<u64> {
+        self.deref().len()
+    }
+}
+
+impl<T: WritableDiskPartition + ?Sized> WritableDiskPartition for Box<T> {
+    fn write(&self, offset: u64, buf: &[u8]) -> Result<usize, Self::Err> {
+        self.deref().write(offset, buf)
+    }
 }
 
-impl<T: DiskPartition> DiskPartition for Arc<T> {
+impl<T: DiskPartition + ?Sized> DiskPartition for Arc<T> {
     type Err = T::Err;
 
     fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Err> {
         self.deref().read(offset, buf)
     }
+
+    fn len(&self) -> Option<u64> {
+        self.deref().len()
+    }
 }
 
+impl<T: WritableDiskPartition + ?Sized> WritableDiskPartition for Arc<T> {
+    fn write(&self, offset: u64, buf: &[u8]) -> Result<usize, Self::Err> {
+        self.deref().write(offset, buf)
+    }
+}
+
+/// An adapter to erase the error type of a [`DiskPartition`].
+///
+/// The errors of the underlying partition will be boxed into [`ErasedError`] so the type of
+/// [`Root`][crate::Root] does not depend on it. This allows partitions with different error types
+/// to be used as the same type (e.g. `Box<dyn DiskPartition<Err = ErasedError>>`).
+///
+/// Note that the error of the underlying partition still need to be `'static` since it will be
+/// used as a source of other errors.
+pub struct ErasedDiskPartition<T>(T);
+
+impl<T> ErasedDiskPartition<T> {
+    pub fn new(inner: T) -> Self {
+        Self(inner)
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: DiskPartition> DiskPartition for ErasedDiskPartition<T> {
+    type Err = ErasedError;
+
+    fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Err> {
+        self.0.read(offset, buf).map_err(ErasedError::new)
+    }
+
+    fn len(&self) -> Option<u64> {
+        self.0.len()
+    }
+}
+
+impl<T: WritableDiskPartition> WritableDiskPartition for ErasedDiskPartition<T> {
+    fn write(&self, offset: u64, buf: &[u8]) -> Result<usize, Self::Err> {
+        self.0.write(offset, buf).map_err(ErasedError::new)
+    }
+}
+
+/// An adapter to hide [`WritableDiskPartition`] of a partition.
+///
+/// The methods that write to the volume require [`WritableDiskPartition`] so they are not
+/// available on a [`Root`][crate::Root] of this partition at compile time, including the methods of
+/// its files and directories. See [`Root::open_read_only()`][crate::Root::open_read_only()].
+pub struct ReadOnly<T>(T);
+
+impl<T> ReadOnly<T> {
+    pub fn new(inner: T) -> Self {
+        Self(inner)
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: DiskPartition> DiskPartition for ReadOnly<T> {
+    type Err = T::Err;
+
+    fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Err> {
+        self.0.read(offset, buf)
+    }
+
+    fn len(&self) -> Option<u64> {
+        self.0.len()
+    }
+}
+
+/// An error of [`ErasedDiskPartition`].
+///
+/// This is transparent so [`Display`] and [`Error::source()`] are forwarded to the boxed error.
+#[derive(Debug)]
+pub struct ErasedError(Box<dyn Error + Send + Sync>);
+
+impl ErasedError {
+    pub fn new(e: impl Error + Send + Sync + 'static) -> Self {
+        Self(Box::new(e))
+    }
+
+    pub fn get_ref(&self) -> &(dyn Error + Send + Sync + 'static) {
+        self.0.as_ref()
+    }
+
+    pub fn into_inner(self) -> Box<dyn Error + Send + Sync> {
+        self.0
+    }
+}
+
+impl Display for ErasedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl Error for ErasedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl PartitionError for ErasedError {
+    fn unexpected_eop() -> Self {
+        Self::new(UnexpectedEop)
+    }
+}
+
+/// An error for [`PartitionError::unexpected_eop()`] of [`ErasedError`].
+#[derive(Debug, thiserror::Error)]
+#[error("unexpected end of partition")]
+struct UnexpectedEop;
+
 #[cfg(feature = "std")]
 impl DiskPartition for std::fs::File {
     type Err = std::io::Error;
@@ -63,6 +255,28 @@ impl DiskPartition for std::fs::File {
     fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Err> {
         std::os::windows::fs::FileExt::seek_read(self, buf, offset)
     }
+
+    /// Returns [`None`] if this is not a regular file (e.g. a block device) since its size is not
+    /// available from the metadata.
+    fn len(&self) -> Option<u64> {
+        match self.metadata() {
+            Ok(v) if v.is_file() => Some(v.len()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl WritableDiskPartition for std::fs::File {
+    #[cfg(unix)]
+    fn write(&self, offset: u64, buf: &[u8]) -> Result<usize, Self::Err> {
+        std::os::unix::fs::FileExt::write_at(self, buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn write(&self, offset: u64, buf: &[u8]) -> Result<usize, Self::Err> {
+        std::os::windows::fs::FileExt::seek_write(self, buf, offset)
+    }
 }
 
 #[cfg(feature = "std")]
@@ -1,33 +1,76 @@
 use crate::cluster::ClustersReader;
 use crate::disk::DiskPartition;
+use crate::fat::Fat;
+use crate::options::OpenOptions;
 use crate::param::Params;
 use crate::timestamp::{Timestamp, Timestamps};
+use crate::upcase::UpcaseTable;
 use crate::FileAttributes;
+use alloc::string::String;
+use alloc::vec::Vec;
 use byteorder::{ByteOrder, LE};
-use std::cmp::min;
-use std::fmt::{Display, Formatter};
+use core::cmp::min;
+use core::fmt::{Display, Formatter};
 use thiserror::Error;
 
 /// Struct to read directory entries.
-pub struct EntriesReader<D, P> {
+pub(crate) struct EntriesReader<D, P> {
     cluster_reader: ClustersReader<D, P>,
-    entry_index: usize,
+    skip_deleted: bool,
 }
 
 impl<D, P> EntriesReader<D, P> {
     pub fn new(cluster_reader: ClustersReader<D, P>) -> Self {
         Self {
             cluster_reader,
-            entry_index: 0,
+            skip_deleted: false,
         }
     }
+
+    /// Sets whether [`EntriesReader::read()`] should skip the entries that are not in use until
+    /// the end of directory entry. The last entry will be returned if the end of the allocation
+    /// was reached while skipping.
+    pub fn set_skip_deleted(&mut self, v: bool) {
+        self.skip_deleted = v;
+    }
+
+    pub fn rewind(&mut self) {
+        self.cluster_reader.rewind();
+    }
+
+    /// Moves to the entry at `offset` bytes. Returns `false` if `offset` is past the end.
+    pub fn seek(&mut self, offset: u64) -> bool {
+        self.cluster_reader.seek(offset)
+    }
+
+    /// Returns `true` if there are no more entries in the allocation.
+    pub fn is_end(&self) -> bool {
+        self.cluster_reader.stream_position() >= self.cluster_reader.data_length()
+    }
 }
 
 impl<D: DiskPartition, P: AsRef<Params>> EntriesReader<D, P> {
     pub fn read(&mut self) -> Result<RawEntry, ReaderError> {
+        loop {
+            let entry = self.read_next()?;
+            let ty = entry.ty();
+
+            if !self.skip_deleted || ty.is_in_use() || ty.is_end_of_directory() || self.is_end() {
+                return Ok(entry);
+            }
+        }
+    }
+
+    fn read_next(&mut self) -> Result<RawEntry, ReaderError> {
+        // A directory that is full of entries has no end of directory entry.
+        if self.is_end() {
+            return Err(ReaderError::EndOfAllocation);
+        }
+
         // Get current cluster and entry index.
         let cluster = self.cluster_reader.cluster();
-        let index = self.entry_index;
+        let offset = self.cluster_reader.stream_position() % self.cluster_reader.cluster_size();
+        let index = (offset / 32) as usize;
 
         // Read directory entry.
         let mut entry = [0u8; 32];
@@ -36,13 +79,6 @@ impl<D: DiskPartition, P: AsRef<Params>> EntriesReader<D, P> {
             return Err(ReaderError::ReadFailed(index, cluster, e));
         }
 
-        // Update entry index.
-        if self.cluster_reader.cluster() != cluster {
-            self.entry_index = 0;
-        } else {
-            self.entry_index += 1;
-        }
-
         Ok(RawEntry {
             index,
             cluster,
@@ -52,47 +88,90 @@ impl<D: DiskPartition, P: AsRef<Params>> EntriesReader<D, P> {
 }
 
 /// Represents a raw directory entry.
-pub(crate) struct RawEntry {
+#[derive(Clone)]
+pub struct RawEntry {
     index: usize,
     cluster: usize,
     data: [u8; 32],
 }
 
 impl RawEntry {
+    /// Creates an unused entry at `index` on `cluster`.
+    pub(crate) fn new(index: usize, cluster: usize) -> Self {
+        Self {
+            index,
+            cluster,
+            data: [0; 32],
+        }
+    }
+
     pub fn ty(&self) -> EntryType {
         EntryType(self.data[0])
     }
 
+    /// Gets the index of this entry within its cluster.
     pub fn index(&self) -> usize {
         self.index
     }
 
+    /// Gets the cluster that contains this entry.
     pub fn cluster(&self) -> usize {
         self.cluster
     }
 
+    /// Gets the 32 bytes of this entry as stored on the disk.
     pub fn data(&self) -> &[u8; 32] {
         &self.data
     }
+
+    /// Clears InUse bit of this entry.
+    pub(crate) fn delete(&mut self) {
+        self.data[0] &= 0x7f;
+    }
+
+    /// Turns this entry into a Volume Label entry. An empty `label` will produce an entry that
+    /// indicates the volume has no label.
+    ///
+    /// `label` must not have more than 11 characters.
+    pub(crate) fn set_volume_label(&mut self, label: &[u16]) {
+        self.data = [0; 32];
+        self.data[0] = if label.is_empty() { 0x03 } else { 0x83 };
+        self.data[1] = label.len() as u8;
+
+        LE::write_u16_into(label, &mut self.data[2..(2 + label.len() * 2)]);
+    }
+
+    /// Gets the offset of this entry in the partition.
+    pub(crate) fn offset(&self, params: &Params) -> Option<u64> {
+        params
+            .cluster_offset(self.cluster)
+            .map(|v| v + self.index as u64 * 32)
+    }
 }
 
 /// Represents a File Directory Entry.
+#[derive(Clone)]
 pub(crate) struct FileEntry {
+    pub index: usize,
+    pub cluster: usize,
     pub name: String,
+    pub name_utf16: Vec<u16>,
     pub attributes: FileAttributes,
     pub stream: StreamEntry,
     pub timestamps: Timestamps,
+    pub entries: Vec<RawEntry>,
 }
 
 impl FileEntry {
     pub fn load<D: DiskPartition, P: AsRef<Params>>(
         raw: &RawEntry,
         reader: &mut EntriesReader<D, P>,
+        upcase: &UpcaseTable,
+        options: &OpenOptions,
     ) -> Result<Self, FileEntryError> {
         // Load fields.
         let data = &raw.data;
         let secondary_count = data[1] as usize;
-        let attributes = FileAttributes(LE::read_u16(&data[4..]));
 
         if secondary_count < 1 {
             return Err(FileEntryError::NoStreamExtension(raw.index, raw.cluster));
@@ -101,27 +180,28 @@ impl FileEntry {
         }
 
         // Read stream extension.
-        let stream = match reader.read() {
+        let raw_stream = match reader.read() {
             Ok(v) => v,
             Err(e) => return Err(FileEntryError::ReadStreamFailed(e)),
         };
 
         // Check if the entry is a stream extension.
-        let ty = stream.ty();
+        let ty = raw_stream.ty();
 
         if !ty.is_critical_secondary(0) {
             return Err(FileEntryError::NotStreamExtension(
-                stream.index,
-                stream.cluster,
+                raw_stream.index,
+                raw_stream.cluster,
             ));
         }
 
-        // Load stream extension.
-        let stream = StreamEntry::load(stream, attributes)?;
+        let mut entries: Vec<RawEntry> = Vec::with_capacity(secondary_count + 1);
+
+        entries.push(raw.clone());
+        entries.push(raw_stream);
 
         // Read file names.
         let name_count = secondary_count - 1;
-        let mut names: Vec<RawEntry> = Vec::with_capacity(name_count);
 
         for i in 0..name_count {
             // Read file name.
@@ -137,42 +217,76 @@ impl FileEntry {
                 return Err(FileEntryError::NotFileName(entry.index, entry.cluster));
             }
 
-            names.push(entry);
+            entries.push(entry);
+        }
+
+        Self::parse(
+            entries,
+            upcase,
+            options.skip_checksums,
+            options.strict_names,
+        )
+    }
+
+    /// Loads a deleted entry set from `entries`. Returns [`None`] if the set is not intact.
+    ///
+    /// The name is always converted lossily since this is used for recovery.
+    pub fn recover(
+        entries: &[RawEntry],
+        upcase: &UpcaseTable,
+        skip_checksums: bool,
+    ) -> Option<Self> {
+        // Restore InUse bit.
+        let mut entries = entries.to_vec();
+
+        for e in &mut entries {
+            e.data[0] |= 0x80;
+        }
+
+        // Check entry types.
+        if entries.len() < 3
+            || entries[0].data[1] as usize != entries.len() - 1
+            || !entries[1].ty().is_critical_secondary(0)
+            || !entries[2..].iter().all(|e| e.ty().is_critical_secondary(1))
+        {
+            return None;
         }
 
-        // TODO: Use div_ceil when https://github.com/rust-lang/rust/issues/88581 stabilized.
-        if names.len() != (stream.name_length + 15 - 1) / 15 {
+        Self::parse(entries, upcase, skip_checksums, false).ok()
+    }
+
+    /// Parses a complete entry set. The type of each entry must be already checked.
+    ///
+    /// A name that is not a valid UTF-16 will be an error if `strict_names` is `true`; otherwise
+    /// the invalid code units will be replaced with `U+FFFD`.
+    fn parse(
+        entries: Vec<RawEntry>,
+        upcase: &UpcaseTable,
+        skip_checksums: bool,
+        strict_names: bool,
+    ) -> Result<Self, FileEntryError> {
+        // Load stream extension.
+        let raw = &entries[0];
+        let data = &raw.data;
+        let attributes = FileAttributes(LE::read_u16(&data[4..]));
+        let stream = StreamEntry::load(&entries[1], attributes)?;
+        let name_count = entries.len() - 2;
+
+        if name_count != stream.name_length.div_ceil(15) {
             return Err(FileEntryError::WrongFileNames(raw.index, raw.cluster));
         }
 
+        // Verify SetChecksum.
+        if !skip_checksums && checksum(&entries) != LE::read_u16(&data[2..]) {
+            return Err(FileEntryError::ChecksumMismatch(raw.index, raw.cluster));
+        }
+
         // Construct a complete file name.
         let mut need = stream.name_length * 2;
-        let mut name = String::with_capacity(15 * names.len());
-
-        // Read timestamps (see https://learn.microsoft.com/en-us/windows/win32/fileio/exfat-specification#74-file-directory-entry)
-        let create_ts = LE::read_u32(&data[8..12]);
-        let last_modified_ts = LE::read_u32(&data[12..16]);
-        let last_accessed_ts = LE::read_u32(&data[16..20]);
-        let create_10_ms_increment = data[20];
-        let last_modified_10_ms_increment = data[21];
-        let create_utc_offset = if ((data[22] >> 7) & 1) == 1 {
-            (data[22] & 0x7F) as i8
-        } else {
-            0
-        };
-        let last_modified_utc_offset = if ((data[23] >> 7) & 1) == 1 {
-            (data[23] & 0x7F) as i8
-        } else {
-            0
-        };
-        let last_accessed_utc_offset = if ((data[24] >> 7) & 1) == 1 {
-            (data[24] & 0x7F) as i8
-        } else {
-            0
-        };
+        let mut name: Vec<u16> = Vec::with_capacity(stream.name_length);
 
-        for entry in names {
-            let data = entry.data;
+        for entry in &entries[2..] {
+            let data = &entry.data;
 
             // Load GeneralSecondaryFlags.
             let general_secondary_flags = SecondaryFlags(data[1]);
@@ -192,39 +306,296 @@ impl FileEntry {
 
             LE::read_u16_into(raw_name, file_name);
 
-            match String::from_utf16(file_name) {
-                Ok(v) => name.push_str(&v),
-                Err(_) => return Err(FileEntryError::InvalidFileName(entry.index, entry.cluster)),
-            }
+            name.extend_from_slice(file_name);
         }
 
+        // Verify NameHash.
+        if !skip_checksums && name_hash(upcase, &name) != stream.name_hash {
+            return Err(FileEntryError::NameHashMismatch(raw.index, raw.cluster));
+        }
+
+        let utf8 = if !strict_names {
+            String::from_utf16_lossy(&name)
+        } else if let Ok(v) = String::from_utf16(&name) {
+            v
+        } else {
+            return Err(FileEntryError::InvalidName {
+                cluster: raw.cluster,
+                index: raw.index,
+            });
+        };
+
+        // Read timestamps (see https://learn.microsoft.com/en-us/windows/win32/fileio/exfat-specification#74-file-directory-entry)
+        let create_ts = LE::read_u32(&data[8..12]);
+        let last_modified_ts = LE::read_u32(&data[12..16]);
+        let last_accessed_ts = LE::read_u32(&data[16..20]);
+        let create_10_ms_increment = data[20];
+        let last_modified_10_ms_increment = data[21];
+
         Ok(Self {
-            name,
+            index: raw.index,
+            cluster: raw.cluster,
+            name: utf8,
+            name_utf16: name,
             attributes,
             stream,
             timestamps: Timestamps::new(
-                Timestamp::new(create_ts, create_10_ms_increment, create_utc_offset),
-                Timestamp::new(
-                    last_modified_ts,
-                    last_modified_10_ms_increment,
-                    last_modified_utc_offset,
-                ),
-                Timestamp::new(last_accessed_ts, 0, last_accessed_utc_offset),
+                Timestamp::from_fields(create_ts, create_10_ms_increment, data[22]),
+                Timestamp::from_fields(last_modified_ts, last_modified_10_ms_increment, data[23]),
+                Timestamp::from_fields(last_accessed_ts, 0, data[24]),
             ),
+            entries,
         })
     }
+
+    /// Creates a new entry set on `slots`.
+    ///
+    /// The number of `slots` must be exactly the number of entries required by `name`.
+    pub fn new(
+        mut slots: Vec<RawEntry>,
+        name: &str,
+        attributes: FileAttributes,
+        timestamps: Timestamps,
+        upcase: &UpcaseTable,
+    ) -> Self {
+        let utf16: Vec<u16> = name.encode_utf16().collect();
+        let name_length = utf16.len();
+        let name_hash = name_hash(upcase, &utf16);
+        let secondary_count = (slots.len() - 1) as u8;
+
+        // Build File entry.
+        let data = &mut slots[0].data;
+
+        *data = [0; 32];
+        data[0] = 0x85;
+        data[1] = secondary_count;
+
+        LE::write_u16(&mut data[4..], attributes.0);
+        encode_timestamps(data, &timestamps);
+
+        // Build Stream Extension entry.
+        let data = &mut slots[1].data;
+
+        *data = [0; 32];
+        data[0] = 0xc0;
+        data[1] = 1;
+        data[3] = name_length as u8;
+
+        LE::write_u16(&mut data[4..], name_hash);
+
+        encode_name(&mut slots[2..], &utf16);
+
+        let mut entry = Self {
+            index: slots[0].index,
+            cluster: slots[0].cluster,
+            name: name.into(),
+            name_utf16: utf16,
+            attributes,
+            stream: StreamEntry {
+                no_fat_chain: false,
+                name_length,
+                name_hash,
+                valid_data_length: 0,
+                alloc: ClusterAllocation {
+                    first_cluster: 0,
+                    data_length: 0,
+                },
+            },
+            timestamps,
+            entries: slots,
+        };
+
+        entry.update_checksum();
+        entry
+    }
+
+    /// Creates a pseudo entry for the root directory, which does not have an entry set.
+    pub fn root(first_cluster: usize, data_length: u64) -> Self {
+        let timestamp = Timestamp::new(0, 0, 0);
+
+        Self {
+            index: 0,
+            cluster: 0,
+            name: String::new(),
+            name_utf16: Vec::new(),
+            attributes: FileAttributes::from_bits(FileAttributes::DIRECTORY),
+            stream: StreamEntry {
+                no_fat_chain: false,
+                name_length: 0,
+                name_hash: 0,
+                valid_data_length: data_length,
+                alloc: ClusterAllocation {
+                    first_cluster,
+                    data_length,
+                },
+            },
+            timestamps: Timestamps::new(timestamp, timestamp, timestamp),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Creates a copy of this entry set on `slots` with a different name.
+    ///
+    /// The number of `slots` must be exactly the number of entries required by `name`.
+    pub fn with_name(&self, mut slots: Vec<RawEntry>, name: &str, upcase: &UpcaseTable) -> Self {
+        let utf16: Vec<u16> = name.encode_utf16().collect();
+        let name_length = utf16.len();
+        let name_hash = name_hash(upcase, &utf16);
+
+        // Copy File entry.
+        slots[0].data = self.entries[0].data;
+        slots[0].data[1] = (slots.len() - 1) as u8;
+
+        // Copy Stream Extension entry.
+        let data = &mut slots[1].data;
+
+        *data = self.entries[1].data;
+        data[3] = name_length as u8;
+
+        LE::write_u16(&mut data[4..], name_hash);
+
+        encode_name(&mut slots[2..], &utf16);
+
+        let mut entry = Self {
+            index: slots[0].index,
+            cluster: slots[0].cluster,
+            name: name.into(),
+            name_utf16: utf16,
+            attributes: self.attributes,
+            stream: StreamEntry {
+                name_length,
+                name_hash,
+                ..self.stream.clone()
+            },
+            timestamps: self.timestamps,
+            entries: slots,
+        };
+
+        entry.update_checksum();
+        entry
+    }
+
+    /// Returns SecondaryCount of the primary entry or zero for the root directory.
+    pub fn secondary_count(&self) -> usize {
+        self.entries.first().map_or(0, |e| e.data[1].into())
+    }
+
+    /// Updates the timestamps in the primary entry and SetChecksum.
+    pub fn set_timestamps(&mut self, timestamps: Timestamps) {
+        encode_timestamps(&mut self.entries[0].data, &timestamps);
+
+        self.timestamps = timestamps;
+        self.update_checksum();
+    }
+
+    /// Updates the allocation in the Stream Extension entry and SetChecksum.
+    pub fn set_allocation(
+        &mut self,
+        first_cluster: usize,
+        no_fat_chain: bool,
+        data_length: u64,
+        valid_data_length: u64,
+    ) {
+        self.stream.no_fat_chain = no_fat_chain;
+        self.stream.valid_data_length = valid_data_length;
+        self.stream.alloc.first_cluster = first_cluster;
+        self.stream.alloc.data_length = data_length;
+
+        // The root directory does not have an entry set.
+        if self.entries.is_empty() {
+            return;
+        }
+
+        let data = &mut self.entries[1].data;
+
+        if no_fat_chain {
+            data[1] |= 2;
+        } else {
+            data[1] &= !2;
+        }
+
+        LE::write_u64(&mut data[8..], valid_data_length);
+        LE::write_u32(&mut data[20..], first_cluster as u32);
+        LE::write_u64(&mut data[24..], data_length);
+
+        self.update_checksum();
+    }
+
+    fn update_checksum(&mut self) {
+        let checksum = checksum(&self.entries);
+
+        LE::write_u16(&mut self.entries[0].data[2..], checksum);
+    }
+}
+
+/// Writes `timestamps` to the fields of File entry.
+fn encode_timestamps(data: &mut [u8; 32], timestamps: &Timestamps) {
+    let created = timestamps.created();
+    let modified = timestamps.modified();
+    let accessed = timestamps.accessed();
+
+    LE::write_u32(&mut data[8..], created.timestamp());
+    LE::write_u32(&mut data[12..], modified.timestamp());
+    LE::write_u32(&mut data[16..], accessed.timestamp());
+
+    data[20] = created.ms_increment();
+    data[21] = modified.ms_increment();
+    data[22] = created.utc_offset_field();
+    data[23] = modified.utc_offset_field();
+    data[24] = accessed.utc_offset_field();
+}
+
+/// Writes `name` to File Name entries.
+fn encode_name(entries: &mut [RawEntry], name: &[u16]) {
+    for (entry, chars) in entries.iter_mut().zip(name.chunks(15)) {
+        let data = &mut entry.data;
+
+        *data = [0; 32];
+        data[0] = 0xc1;
+
+        LE::write_u16_into(chars, &mut data[2..(2 + chars.len() * 2)]);
+    }
+}
+
+/// Calculates NameHash of `name`.
+fn name_hash(upcase: &UpcaseTable, name: &[u16]) -> u16 {
+    name.iter().fold(0u16, |h, &c| {
+        let [lo, hi] = upcase.map(c).to_le_bytes();
+        let h = h.rotate_right(1).wrapping_add(lo.into());
+
+        h.rotate_right(1).wrapping_add(hi.into())
+    })
+}
+
+/// Calculates SetChecksum of the entry set.
+fn checksum(entries: &[RawEntry]) -> u16 {
+    let mut checksum = 0u16;
+
+    for (i, entry) in entries.iter().enumerate() {
+        for (j, &b) in entry.data.iter().enumerate() {
+            if i == 0 && (j == 2 || j == 3) {
+                continue;
+            }
+
+            checksum = checksum.rotate_right(1).wrapping_add(b.into());
+        }
+    }
+
+    checksum
 }
 
 /// Represents a Stream Extension Directory Entry.
-pub(crate) struct StreamEntry {
+#[derive(Debug, Clone)]
+pub struct StreamEntry {
     no_fat_chain: bool,
     name_length: usize,
+    name_hash: u16,
     valid_data_length: u64,
     alloc: ClusterAllocation,
 }
 
 impl StreamEntry {
-    fn load(raw: RawEntry, attrs: FileAttributes) -> Result<Self, FileEntryError> {
+    fn load(raw: &RawEntry, attrs: FileAttributes) -> Result<Self, FileEntryError> {
         // Load GeneralSecondaryFlags.
         let data = &raw.data;
         let general_secondary_flags = SecondaryFlags(data[1]);
@@ -246,9 +617,12 @@ impl StreamEntry {
             ));
         }
 
+        // Load NameHash.
+        let name_hash = LE::read_u16(&data[4..]);
+
         // Load ValidDataLength and cluster allocation.
         let valid_data_length = LE::read_u64(&data[8..]);
-        let alloc = match ClusterAllocation::load(&raw) {
+        let alloc = match ClusterAllocation::load(raw) {
             Ok(v) => v,
             Err(_) => {
                 return Err(FileEntryError::InvalidStreamExtension(
@@ -275,38 +649,90 @@ impl StreamEntry {
         Ok(StreamEntry {
             no_fat_chain: general_secondary_flags.no_fat_chain(),
             name_length,
+            name_hash,
             valid_data_length,
             alloc,
         })
     }
 
+    /// Returns `true` if NoFatChain flag is set.
     pub fn no_fat_chain(&self) -> bool {
         self.no_fat_chain
     }
 
+    /// Returns NameLength, which is the number of UTF-16 code units in the name.
+    pub fn name_length(&self) -> usize {
+        self.name_length
+    }
+
+    pub fn name_hash(&self) -> u16 {
+        self.name_hash
+    }
+
     pub fn valid_data_length(&self) -> u64 {
         self.valid_data_length
     }
 
+    pub fn first_cluster(&self) -> usize {
+        self.alloc.first_cluster
+    }
+
+    pub fn data_length(&self) -> u64 {
+        self.alloc.data_length
+    }
+
     pub fn allocation(&self) -> &ClusterAllocation {
         &self.alloc
     }
+
+    /// Gets all clusters that was allocated for this stream. Returns the cluster with an invalid
+    /// FAT entry if the FAT chain is not valid.
+    pub(crate) fn clusters(&self, params: &Params, fat: &Fat) -> Result<Vec<usize>, usize> {
+        let first_cluster = self.alloc.first_cluster;
+
+        if first_cluster == 0 {
+            Ok(Vec::new())
+        } else if self.no_fat_chain {
+            // DataLength may not be validated yet so limit the clusters to the Cluster Heap.
+            let available = (params.cluster_count + 2).saturating_sub(first_cluster);
+            let count = self.alloc.data_length.div_ceil(params.cluster_size());
+            let count = usize::try_from(count).map_or(available, |v| min(v, available));
+
+            Ok((first_cluster..(first_cluster + count)).collect())
+        } else {
+            fat.get_valid_cluster_chain(first_cluster)
+        }
+    }
 }
 
 /// Encapsulate EntryType field of the directory entry.
 #[derive(Debug, Clone, Copy)]
 #[repr(transparent)]
-pub(crate) struct EntryType(u8);
+pub struct EntryType(u8);
 
 impl EntryType {
     pub const PRIMARY: u8 = 0;
     pub const SECONDARY: u8 = 1;
     pub const CRITICAL: u8 = 0;
+    pub const BENIGN: u8 = 1;
 
     pub fn is_regular(self) -> bool {
         self.0 >= 0x81
     }
 
+    pub fn is_in_use(self) -> bool {
+        (self.0 & 0x80) != 0
+    }
+
+    pub fn is_end_of_directory(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if this is a Volume Label entry without a label.
+    pub(crate) fn is_empty_volume_label(self) -> bool {
+        self.0 == 0x03
+    }
+
     pub fn type_code(self) -> u8 {
         self.0 & 0x1f
     }
@@ -328,7 +754,7 @@ impl EntryType {
 }
 
 impl Display for EntryType {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         if self.is_regular() {
             if self.type_importance() == Self::CRITICAL {
                 f.write_str("critical ")?;
@@ -365,7 +791,7 @@ impl SecondaryFlags {
 }
 
 /// Represents FirstCluster and DataLength fields in the Directory Entry.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ClusterAllocation {
     first_cluster: usize,
     data_length: u64,
@@ -393,17 +819,19 @@ impl ClusterAllocation {
         })
     }
 
-    pub(crate) fn first_cluster(&self) -> usize {
+    /// Returns FirstCluster, which is zero if no cluster is allocated.
+    pub fn first_cluster(&self) -> usize {
         self.first_cluster
     }
 
-    pub(crate) fn data_length(&self) -> u64 {
+    /// Returns DataLength, in bytes.
+    pub fn data_length(&self) -> u64 {
         self.data_length
     }
 }
 
 impl Display for ClusterAllocation {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}:{}", self.first_cluster, self.data_length)
     }
 }
@@ -412,7 +840,10 @@ impl Display for ClusterAllocation {
 #[derive(Debug, Error)]
 pub enum ReaderError {
     #[error("cannot read entry #{0} on cluster #{1}")]
-    ReadFailed(usize, usize, #[source] std::io::Error),
+    ReadFailed(usize, usize, #[source] crate::io::ReadError),
+
+    #[error("no more entries in the allocation")]
+    EndOfAllocation,
 }
 
 /// Represents an error for [`load()`][FileEntry::load()].
@@ -444,6 +875,15 @@ pub enum FileEntryError {
 
     #[error("entry #{0} on cluster #{1} is not a valid file name")]
     InvalidFileName(usize, usize),
+
+    #[error("entry #{index} on cluster #{cluster} has a name that is not a valid UTF-16")]
+    InvalidName { cluster: usize, index: usize },
+
+    #[error("entry #{0} on cluster #{1} has mismatched NameHash")]
+    NameHashMismatch(usize, usize),
+
+    #[error("entry #{0} on cluster #{1} has mismatched SetChecksum")]
+    ChecksumMismatch(usize, usize),
 }
 
 /// Represents an error for [`load()`][ClusterAllocation::load()].
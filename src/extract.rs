@@ -0,0 +1,134 @@
+use crate::disk::DiskPartition;
+use crate::timestamp::Timestamps;
+use crate::{DirectoryError, Item, Root};
+use filetime::FileTime;
+use std::fs::OpenOptions;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+impl<P: DiskPartition> Root<P> {
+    /// Recreates the whole tree of this volume in `dest`, which will be created if it does not
+    /// exist.
+    ///
+    /// Timestamps of the files and directories will be applied as well. This will never overwrite
+    /// anything in `dest` so it will fail with [`ExtractError::AlreadyExists`] if a name is already
+    /// in use (e.g. two names that differ only in case on a case-insensitive host).
+    pub fn extract_to(&self, dest: &Path) -> Result<(), ExtractError> {
+        if let Err(e) = std::fs::create_dir_all(dest) {
+            return Err(ExtractError::CreateFailed(dest.to_owned(), e));
+        }
+
+        let items = match self.root.open() {
+            Ok(v) => v,
+            Err(e) => return Err(ExtractError::OpenDirectoryFailed(String::from("/"), e)),
+        };
+
+        extract_items(&items, "/", dest)
+    }
+}
+
+fn extract_items<P: DiskPartition>(
+    items: &[Item<P>],
+    parent: &str,
+    dest: &Path,
+) -> Result<(), ExtractError> {
+    for item in items {
+        // Check if the name can be used on the host.
+        let name = item.name();
+        let path = format!("{}{}", parent, name);
+        let mut components = Path::new(name).components();
+
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(v)), None) if v == name && !name.contains('\0') => {}
+            _ => return Err(ExtractError::InvalidName(path)),
+        }
+
+        let target = dest.join(name);
+
+        match item {
+            Item::Directory(d) => {
+                match std::fs::create_dir(&target) {
+                    Ok(_) => {}
+                    Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                        return Err(ExtractError::AlreadyExists(target));
+                    }
+                    Err(e) => return Err(ExtractError::CreateFailed(target, e)),
+                }
+
+                let items = match d.open() {
+                    Ok(v) => v,
+                    Err(e) => return Err(ExtractError::OpenDirectoryFailed(path, e)),
+                };
+
+                extract_items(&items, &format!("{}/", path), &target)?;
+                set_timestamps(&target, d.timestamps())?;
+            }
+            Item::File(f) => {
+                let mut host = match OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&target)
+                {
+                    Ok(v) => v,
+                    Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                        return Err(ExtractError::AlreadyExists(target));
+                    }
+                    Err(e) => return Err(ExtractError::CreateFailed(target, e)),
+                };
+
+                if let Err(e) = f.clone_reader().copy_to(&mut host) {
+                    return Err(ExtractError::CopyFailed(path, e));
+                }
+
+                drop(host);
+                set_timestamps(&target, f.timestamps())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn set_timestamps(path: &Path, timestamps: &Timestamps) -> Result<(), ExtractError> {
+    // Skip the timestamps that are not valid.
+    let accessed = timestamps.accessed().to_system_time();
+    let modified = timestamps.modified().to_system_time();
+    let result = match (accessed, modified) {
+        (Some(a), Some(m)) => filetime::set_file_times(
+            path,
+            FileTime::from_system_time(a),
+            FileTime::from_system_time(m),
+        ),
+        (None, Some(m)) => filetime::set_file_mtime(path, FileTime::from_system_time(m)),
+        (Some(a), None) => filetime::set_file_atime(path, FileTime::from_system_time(a)),
+        (None, None) => return Ok(()),
+    };
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(ExtractError::SetTimestampsFailed(path.to_owned(), e)),
+    }
+}
+
+/// Represents an error for [`Root::extract_to()`].
+#[derive(Debug, Error)]
+pub enum ExtractError {
+    #[error("{0} is not a valid name on the host")]
+    InvalidName(String),
+
+    #[error("{0} already exists")]
+    AlreadyExists(PathBuf),
+
+    #[error("cannot create {0}")]
+    CreateFailed(PathBuf, #[source] std::io::Error),
+
+    #[error("cannot open {0}")]
+    OpenDirectoryFailed(String, #[source] DirectoryError),
+
+    #[error("cannot copy {0}")]
+    CopyFailed(String, #[source] std::io::Error),
+
+    #[error("cannot set timestamps of {0}")]
+    SetTimestampsFailed(PathBuf, #[source] std::io::Error),
+}
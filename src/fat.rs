@@ -1,11 +1,15 @@
-use crate::disk::DiskPartition;
+use crate::disk::{DiskPartition, WritableDiskPartition};
 use crate::param::Params;
+use alloc::vec;
+use alloc::vec::Vec;
 use byteorder::{ByteOrder, LE};
 use core::fmt::Debug;
+use spin::{RwLock, RwLockReadGuard};
 use thiserror::Error;
 
 pub(crate) struct Fat {
-    entries: Vec<u32>,
+    entries: RwLock<Vec<u32>>,
+    offset: u64,
 }
 
 impl Fat {
@@ -28,9 +32,12 @@ impl Fat {
             None => return Err(LoadError::InvalidFatOffset),
         };
 
-        // Load entries.
+        // Load entries. The size may not fit in usize on a 32-bit target.
         let count = params.cluster_count + 2;
-        let mut data = vec![0u8; count * 4];
+        let mut data = match count.checked_mul(4) {
+            Some(v) => vec![0u8; v],
+            None => return Err(LoadError::InvalidFatLength),
+        };
 
         if let Err(e) = partition.read_exact(offset, &mut data) {
             return Err(LoadError::ReadFailed(offset, e));
@@ -41,19 +48,98 @@ impl Fat {
 
         LE::read_u32_into(&data, &mut entries);
 
-        Ok(Self { entries })
+        Ok(Self {
+            entries: RwLock::new(entries),
+            offset,
+        })
+    }
+
+    /// Returns the index of the first entry that is different from `other`.
+    pub fn first_mismatch(&self, other: &Self) -> Option<usize> {
+        let a = self.entries.read();
+        let b = other.entries.read();
+
+        a.iter().zip(b.iter()).position(|(a, b)| a != b)
     }
 
     pub fn get_cluster_chain(&self, first: usize) -> ClusterChain<'_> {
         ClusterChain {
-            entries: &self.entries,
+            entries: self.entries.read(),
             next: first,
         }
     }
+
+    /// Same as [`Fat::get_cluster_chain()`] but the chain must be terminated with an end of chain
+    /// marker.
+    ///
+    /// Returns the cluster with an invalid entry if the chain points to an invalid cluster or it
+    /// contains a loop. The chain will be empty if `first` is not a valid cluster.
+    pub fn get_valid_cluster_chain(&self, first: usize) -> Result<Vec<usize>, usize> {
+        let entries = self.entries.read();
+        let mut chain = Vec::new();
+        let mut next = first;
+
+        if next < 2 || next >= entries.len() {
+            return Ok(chain);
+        }
+
+        loop {
+            let value = entries[next];
+
+            chain.push(next);
+
+            if value == 0xffffffff {
+                break;
+            } else if value < 2 || value as usize >= entries.len() || chain.len() >= entries.len() {
+                return Err(next);
+            }
+
+            next = value as usize;
+        }
+
+        Ok(chain)
+    }
+
+    /// Sets the entry for `cluster` to `value` and write it to the partition.
+    pub fn set<P: WritableDiskPartition>(
+        &self,
+        partition: &P,
+        cluster: usize,
+        value: u32,
+    ) -> Result<(), WriteError<P>> {
+        let mut entries = self.entries.write();
+        let offset = self.offset + cluster as u64 * 4;
+
+        if let Err(e) = partition.write_all(offset, &value.to_le_bytes()) {
+            return Err(WriteError::WriteFailed(offset, e));
+        }
+
+        entries[cluster] = value;
+
+        Ok(())
+    }
+
+    /// Links `clusters` together and mark the last one as the end of chain.
+    pub fn set_chain<P: WritableDiskPartition>(
+        &self,
+        partition: &P,
+        clusters: &[usize],
+    ) -> Result<(), WriteError<P>> {
+        for (i, &cluster) in clusters.iter().enumerate() {
+            let next = match clusters.get(i + 1) {
+                Some(&v) => v as u32,
+                None => 0xffffffff,
+            };
+
+            self.set(partition, cluster, next)?;
+        }
+
+        Ok(())
+    }
 }
 
 pub(crate) struct ClusterChain<'fat> {
-    entries: &'fat [u32],
+    entries: RwLockReadGuard<'fat, Vec<u32>>,
     next: usize,
 }
 
@@ -62,7 +148,7 @@ impl<'fat> Iterator for ClusterChain<'fat> {
 
     fn next(&mut self) -> Option<Self::Item> {
         // Check next entry.
-        let entries = self.entries;
+        let entries = &self.entries;
         let next = self.next;
 
         if next < 2 || next >= entries.len() || entries[next] == 0xfffffff7 {
@@ -90,7 +176,7 @@ pub enum LoadError<P: DiskPartition> {
 }
 
 impl<P: DiskPartition> Debug for LoadError<P> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::InvalidFatLength => write!(f, "InvalidFatLength"),
             Self::InvalidFatOffset => write!(f, "InvalidFatOffset"),
@@ -100,3 +186,22 @@ impl<P: DiskPartition> Debug for LoadError<P> {
         }
     }
 }
+
+/// Represents an error for [`Fat::set()`].
+#[derive(Error)]
+pub enum WriteError<P: DiskPartition> {
+    #[error("cannot write the data at {0:#x}")]
+    WriteFailed(u64, #[source] P::Err),
+}
+
+impl<P: DiskPartition> Debug for WriteError<P> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::WriteFailed(arg0, arg1) => f
+                .debug_tuple("WriteFailed")
+                .field(arg0)
+                .field(arg1)
+                .finish(),
+        }
+    }
+}
@@ -1,60 +1,152 @@
-use crate::cluster::ClustersReader;
-use crate::disk::DiskPartition;
-use crate::entries::StreamEntry;
+use crate::bitmap::{AllocError, Bitmap};
+use crate::cluster::{seek_offset, ClusterCache, ClustersReader};
+use crate::crc32::Crc32;
+use crate::disk::{DiskPartition, WritableDiskPartition};
+use crate::entries::{FileEntry, StreamEntry};
 use crate::fat::Fat;
+use crate::io::{Read, ReadError, Seek, SeekError, SeekFrom, TypedReadError};
 use crate::param::Params;
 use crate::timestamp::Timestamps;
+use crate::{FileAttributes, Metadata};
 use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
 use core::cmp::min;
+use core::fmt::Debug;
+use core::hash::Hasher;
 use thiserror::Error;
 
 /// Represents a file in an exFAT filesystem.
+///
+/// A file has two lengths: [`File::len()`] is the amount of data that was written (ValidDataLength)
+/// and [`File::allocated_len()`] is the size of the allocation (DataLength). Reading the file will
+/// return the data up to [`File::allocated_len()`] with the data after [`File::len()`] as zeros.
 pub struct File<D> {
-    name: String,
+    disk: Arc<D>,
+    params: Arc<Params>,
+    fat: Arc<Fat>,
+    cache: Option<Arc<ClusterCache>>,
+    bitmap: Arc<Bitmap>,
+    entry: FileEntry,
     len: u64,
     reader: Option<ClustersReader<Arc<D>, Arc<Params>>>,
-    timestamps: Timestamps,
+    offset: u64, // Position when the file has no clusters.
+    readahead: usize,
 }
 
 impl<D> File<D> {
     pub(crate) fn new(
         disk: &Arc<D>,
         params: &Arc<Params>,
-        fat: &Fat,
-        name: String,
-        stream: StreamEntry,
-        timestamps: Timestamps,
+        fat: &Arc<Fat>,
+        cache: &Option<Arc<ClusterCache>>,
+        bitmap: &Arc<Bitmap>,
+        entry: FileEntry,
     ) -> Result<Self, NewError> {
         // Create a cluster reader.
-        let alloc = stream.allocation();
-        let first_cluster = alloc.first_cluster();
-        let len = stream.valid_data_length();
-        let reader = if first_cluster == 0 {
-            None
-        } else {
-            match ClustersReader::new(
-                disk.clone(),
-                params.clone(),
-                fat,
-                first_cluster,
-                Some(len),
-                Some(stream.no_fat_chain()),
-            ) {
-                Ok(v) => Some(v),
-                Err(e) => return Err(NewError::CreateClustersReaderFailed(first_cluster, len, e)),
+        let len = entry.stream.valid_data_length();
+        let reader = match Self::create_reader(disk, params, fat, cache, &entry) {
+            Ok(v) => v,
+            Err(e) => {
+                let alloc = entry.stream.allocation();
+                let first_cluster = alloc.first_cluster();
+                let data_length = alloc.data_length();
+
+                return Err(NewError::CreateClustersReaderFailed(
+                    first_cluster,
+                    data_length,
+                    e,
+                ));
             }
         };
 
         Ok(Self {
-            name,
+            disk: disk.clone(),
+            params: params.clone(),
+            fat: fat.clone(),
+            cache: cache.clone(),
+            bitmap: bitmap.clone(),
+            entry,
             len,
             reader,
-            timestamps,
+            offset: 0,
+            readahead: 0,
         })
     }
 
+    #[allow(clippy::type_complexity)]
+    fn create_reader(
+        disk: &Arc<D>,
+        params: &Arc<Params>,
+        fat: &Fat,
+        cache: &Option<Arc<ClusterCache>>,
+        entry: &FileEntry,
+    ) -> Result<Option<ClustersReader<Arc<D>, Arc<Params>>>, crate::cluster::NewError> {
+        let stream = &entry.stream;
+        let first_cluster = stream.allocation().first_cluster();
+
+        if first_cluster == 0 {
+            return Ok(None);
+        }
+
+        let mut reader = ClustersReader::new(
+            disk.clone(),
+            params.clone(),
+            fat,
+            first_cluster,
+            Some(stream.allocation().data_length()),
+            Some(stream.no_fat_chain()),
+        )?;
+
+        reader.set_valid_data_length(stream.valid_data_length());
+        reader.set_cache(cache.clone());
+
+        Ok(Some(reader))
+    }
+
     pub fn name(&self) -> &str {
-        self.name.as_ref()
+        self.entry.name.as_ref()
+    }
+
+    /// Returns the name as UTF-16 code units stored on the volume.
+    ///
+    /// This is the same as [`File::name()`] unless the name is not a valid UTF-16, which has the
+    /// invalid code units replaced with `U+FFFD` in [`File::name()`].
+    pub fn name_utf16(&self) -> &[u16] {
+        &self.entry.name_utf16
+    }
+
+    pub fn attributes(&self) -> FileAttributes {
+        self.entry.attributes
+    }
+
+    /// Returns the cluster and the index of the File Directory Entry for this file.
+    pub fn entry_location(&self) -> (usize, usize) {
+        (self.entry.cluster, self.entry.index)
+    }
+
+    /// Returns SecondaryCount of the File Directory Entry for this file.
+    pub fn secondary_count(&self) -> usize {
+        self.entry.secondary_count()
+    }
+
+    pub fn metadata(&self) -> Metadata {
+        Metadata::new(&self.entry)
+    }
+
+    pub fn stream_entry(&self) -> &StreamEntry {
+        &self.entry.stream
+    }
+
+    /// Returns all clusters that were allocated for this file. Returns the cluster with an invalid
+    /// FAT entry if the FAT chain is not valid.
+    pub(crate) fn clusters(&self) -> Result<Vec<usize>, usize> {
+        self.entry.stream.clusters(&self.params, &self.fat)
+    }
+
+    /// Returns `true` if NoFatChain flag is set, which means the clusters are contiguous.
+    pub fn is_no_fat_chain(&self) -> bool {
+        self.entry.stream.no_fat_chain()
     }
 
     pub fn is_empty(&self) -> bool {
@@ -65,73 +157,394 @@ impl<D> File<D> {
         self.len
     }
 
+    /// Returns DataLength of this file, which can be larger than [`File::len()`].
+    pub fn allocated_len(&self) -> u64 {
+        self.entry.stream.allocation().data_length()
+    }
+
+    /// Returns the total size of the clusters occupied by this file.
+    ///
+    /// Unlike [`File::allocated_len()`] this is computed from the cluster chain so it is always a
+    /// multiple of the cluster size.
+    pub fn allocated_size(&self) -> Result<u64, ClustersError> {
+        match self.clusters() {
+            Ok(v) => Ok(v.len() as u64 * self.params.cluster_size()),
+            Err(e) => Err(ClustersError::CorruptClusterChain(e)),
+        }
+    }
+
+    /// Returns the location of the data in the partition as a list of `(offset, length)` in bytes.
+    ///
+    /// Contiguous clusters are merged into a single run. The runs cover [`File::allocated_len()`]
+    /// bytes and stop at the first cluster that is outside the Cluster Heap.
+    pub fn extents(&self) -> Result<Vec<(u64, u64)>, ClustersError> {
+        let cluster_size = self.params.cluster_size();
+        let mut remaining = self.allocated_len();
+        let mut runs: Vec<(u64, u64)> = Vec::new();
+        let clusters = match self.clusters() {
+            Ok(v) => v,
+            Err(e) => return Err(ClustersError::CorruptClusterChain(e)),
+        };
+
+        for cluster in clusters {
+            if remaining == 0 {
+                break;
+            }
+
+            let offset = match self.params.cluster_offset(cluster) {
+                Some(v) => v,
+                None => break,
+            };
+
+            let len = min(remaining, cluster_size);
+
+            match runs.last_mut() {
+                Some((o, l)) if *o + *l == offset => *l += len,
+                _ => runs.push((offset, len)),
+            }
+
+            remaining -= len;
+        }
+
+        Ok(runs)
+    }
+
+    /// Returns the number of bytes from the current position to the end of this file.
+    pub fn remaining(&self) -> u64 {
+        self.reader.as_ref().map_or(0, |r| r.remaining())
+    }
+
     pub fn timestamps(&self) -> &Timestamps {
-        &self.timestamps
+        &self.entry.timestamps
+    }
+
+    /// Creates another [`File`] for the same file with its own position, starting at the
+    /// beginning.
+    ///
+    /// The returned [`File`] has a copy of the cluster chain at the time of calling. Any
+    /// change made with [`File::set_len()`] later will not be visible to the other.
+    pub fn clone_reader(&self) -> Self {
+        let mut reader = self.reader.clone();
+
+        if let Some(r) = &mut reader {
+            r.rewind();
+        }
+
+        Self {
+            disk: self.disk.clone(),
+            params: self.params.clone(),
+            fat: self.fat.clone(),
+            cache: self.cache.clone(),
+            bitmap: self.bitmap.clone(),
+            entry: self.entry.clone(),
+            len: self.len,
+            reader,
+            offset: 0,
+            readahead: self.readahead,
+        }
+    }
+
+    /// Reads up to `clusters` contiguous clusters at once into an internal buffer when the reads
+    /// are sequential. Zero disables read-ahead, which is the default.
+    ///
+    /// This reduces the number of reads on the partition when the file is read with small buffers.
+    /// Read-ahead is not used when the cluster cache is enabled with
+    /// [`OpenOptions::cache_size()`][crate::OpenOptions::cache_size()].
+    pub fn with_readahead(mut self, clusters: usize) -> Self {
+        self.readahead = clusters;
+
+        if let Some(r) = &mut self.reader {
+            r.set_readahead(clusters);
+        }
+
+        self
     }
 }
 
-#[cfg(feature = "std")]
-impl<D> std::io::Seek for File<D> {
-    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
-        use std::io::{Error, ErrorKind, SeekFrom};
+impl<D: DiskPartition> File<D> {
+    /// Same as [`Read::read()`] but the error from the partition is not boxed.
+    pub fn read_typed(&mut self, buf: &mut [u8]) -> Result<usize, TypedReadError<D::Err>> {
+        match &mut self.reader {
+            Some(v) => v.read_typed(buf),
+            None => Ok(0),
+        }
+    }
 
-        // Check if empty file.
-        let r = match &mut self.reader {
+    /// Feeds the data of this file from the beginning to [`File::len()`] into `hasher`.
+    ///
+    /// The data is read one cluster at a time. The current position will be at [`File::len()`]
+    /// on success.
+    pub fn hash<H: Hasher>(&mut self, hasher: &mut H) -> Result<(), ReadError> {
+        let mut buf = vec![0u8; self.params.cluster_size() as usize];
+        let mut remaining = self.len;
+
+        if let Some(r) = &mut self.reader {
+            r.rewind();
+        }
+
+        while remaining != 0 {
+            let len = min(remaining, buf.len() as u64) as usize;
+
+            Read::read_exact(self, &mut buf[..len])?;
+            hasher.write(&buf[..len]);
+
+            remaining -= len as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Computes CRC-32 (IEEE 802.3) of the data in this file. See [`File::hash()`] for more
+    /// details.
+    pub fn crc32(&mut self) -> Result<u32, ReadError> {
+        let mut hasher = Crc32::new();
+
+        self.hash(&mut hasher)?;
+
+        Ok(hasher.finish() as u32)
+    }
+}
+
+impl<D: WritableDiskPartition> File<D> {
+    /// Updates the timestamps of this file on the partition.
+    pub fn set_timestamps(&mut self, timestamps: Timestamps) -> Result<(), SetTimestampsError<D>> {
+        self.entry.set_timestamps(timestamps);
+
+        if let Some(c) = &self.cache {
+            c.clear();
+        }
+
+        // Write the primary entry. SetChecksum also live in this entry.
+        let entry = &self.entry.entries[0];
+        let offset = match entry.offset(&self.params) {
             Some(v) => v,
-            None => return std::io::empty().seek(pos),
+            None => return Err(SetTimestampsError::InvalidEntryLocation),
         };
 
-        // Get absolute offset.
-        let o = match pos {
-            SeekFrom::Start(v) => min(v, r.data_length()),
-            SeekFrom::End(v) => {
-                if v >= 0 {
-                    r.data_length()
-                } else if let Some(v) = r.data_length().checked_sub(v.unsigned_abs()) {
-                    v
-                } else {
-                    return Err(Error::from(ErrorKind::InvalidInput));
-                }
+        if let Err(e) = self.disk.write_all(offset, entry.data()) {
+            return Err(SetTimestampsError::WriteFailed(offset, e));
+        }
+
+        Ok(())
+    }
+
+    /// Truncates or extends this file to `new_len` bytes.
+    ///
+    /// The extended region will be filled with zeros. The current position will be moved to
+    /// `new_len` if it is past the new size.
+    pub fn set_len(&mut self, new_len: u64) -> Result<(), SetLenError<D>> {
+        // Get current clusters.
+        let disk = self.disk.as_ref();
+
+        if let Some(c) = &self.cache {
+            c.clear();
+        }
+
+        let cluster_size = self.params.cluster_size();
+        let mut no_fat_chain = self.entry.stream.no_fat_chain();
+        let mut chain = match self.clusters() {
+            Ok(v) => v,
+            Err(e) => return Err(SetLenError::CorruptClusterChain(e)),
+        };
+
+        // Allocate or free clusters.
+        let count = match new_len.div_ceil(cluster_size).try_into() {
+            Ok(v) => v,
+            Err(_) => return Err(SetLenError::AllocateFailed(AllocError::NoSpace)),
+        };
+
+        if count > chain.len() {
+            no_fat_chain = match self
+                .bitmap
+                .grow(disk, &self.fat, &mut chain, no_fat_chain, count)
+            {
+                Ok(v) => v,
+                Err(e) => return Err(SetLenError::AllocateFailed(e)),
+            };
+        } else if count < chain.len() {
+            if let Err(e) = self
+                .bitmap
+                .shrink(disk, &self.fat, &mut chain, no_fat_chain, count)
+            {
+                return Err(SetLenError::AllocateFailed(e));
             }
-            SeekFrom::Current(v) => v.try_into().map_or_else(
-                |_| {
-                    r.stream_position()
-                        .checked_sub(v.unsigned_abs())
-                        .ok_or_else(|| Error::from(ErrorKind::InvalidInput))
-                },
-                |v| Ok(min(r.stream_position().saturating_add(v), r.data_length())),
-            )?,
+        }
+
+        // Fill the extended region with zeros.
+        if new_len > self.len {
+            self.zero(&chain, self.len, new_len)?;
+        }
+
+        // Update the entry set.
+        let first_cluster = chain.first().copied().unwrap_or(0);
+
+        self.entry
+            .set_allocation(first_cluster, no_fat_chain, new_len, new_len);
+
+        for entry in &self.entry.entries[..2] {
+            let offset = match entry.offset(&self.params) {
+                Some(v) => v,
+                None => return Err(SetLenError::InvalidEntryLocation),
+            };
+
+            if let Err(e) = self.disk.write_all(offset, entry.data()) {
+                return Err(SetLenError::WriteFailed(offset, e));
+            }
+        }
+
+        // Re-create the reader.
+        let position = self
+            .reader
+            .as_ref()
+            .map_or(self.offset, |r| r.stream_position());
+
+        self.reader = match Self::create_reader(
+            &self.disk,
+            &self.params,
+            &self.fat,
+            &self.cache,
+            &self.entry,
+        ) {
+            Ok(v) => v,
+            Err(e) => return Err(SetLenError::CreateClustersReaderFailed(e)),
         };
 
-        assert!(r.seek(o));
+        self.offset = min(position, new_len);
+
+        if let Some(r) = &mut self.reader {
+            r.set_readahead(self.readahead);
+            r.seek(self.offset);
+        }
+
+        self.len = new_len;
 
-        Ok(o)
+        Ok(())
     }
 
-    fn rewind(&mut self) -> std::io::Result<()> {
+    /// Writes zeros from `from` to `to` on the data of `chain`.
+    fn zero(&self, chain: &[usize], mut from: u64, to: u64) -> Result<(), SetLenError<D>> {
+        let cluster_size = self.params.cluster_size();
+        let zeros = vec![0u8; min(cluster_size, 0x10000) as usize];
+
+        while from < to {
+            let cluster = chain[(from / cluster_size) as usize];
+            let offset = match self.params.cluster_offset(cluster) {
+                Some(v) => v + from % cluster_size,
+                None => return Err(SetLenError::InvalidCluster(cluster)),
+            };
+
+            let len = min(to - from, cluster_size - from % cluster_size);
+            let len = min(len, zeros.len() as u64);
+
+            if let Err(e) = self.disk.write_all(offset, &zeros[..(len as usize)]) {
+                return Err(SetLenError::WriteFailed(offset, e));
+            }
+
+            from += len;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<D: DiskPartition> File<D> {
+    /// Reads the whole content of this file from the beginning.
+    ///
+    /// An error with [`std::io::ErrorKind::UnexpectedEof`] will be returned if the data is
+    /// shorter than [`File::len()`].
+    pub fn read_to_vec(&mut self) -> std::io::Result<Vec<u8>> {
+        use std::io::{Error, ErrorKind};
+
+        let mut data = match usize::try_from(self.len) {
+            Ok(v) => vec![0u8; v],
+            Err(_) => return Err(Error::from(ErrorKind::OutOfMemory)),
+        };
+
+        Seek::rewind(self)?;
+        Read::read_exact(self, &mut data)?;
+
+        Ok(data)
+    }
+
+    /// Writes the data of this file from the beginning to [`File::len()`] into `w`. Returns the
+    /// number of bytes written.
+    ///
+    /// The data is read one cluster at a time into a single buffer. The current position will be
+    /// at [`File::len()`] on success.
+    pub fn copy_to(&mut self, w: &mut impl std::io::Write) -> std::io::Result<u64> {
+        let mut buf = vec![0u8; self.params.cluster_size() as usize];
+        let mut remaining = self.len;
+
+        Seek::rewind(self)?;
+
+        while remaining != 0 {
+            let len = min(remaining, buf.len() as u64) as usize;
+
+            Read::read_exact(self, &mut buf[..len])?;
+            w.write_all(&buf[..len])?;
+
+            remaining -= len as u64;
+        }
+
+        Ok(self.len)
+    }
+}
+
+/// Two files are equal if they have the same [`File::entry_location()`]. This is only meaningful
+/// for files on the same volume.
+impl<D> PartialEq for File<D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entry_location() == other.entry_location()
+    }
+}
+
+impl<D> Eq for File<D> {}
+
+impl<D> core::hash::Hash for File<D> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        core::hash::Hash::hash(&self.entry_location(), state);
+    }
+}
+
+impl<D> Seek for File<D> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, SeekError> {
+        // Check if empty file.
         let r = match &mut self.reader {
             Some(v) => v,
-            None => return Ok(()),
+            None => {
+                self.offset = seek_offset(self.offset, 0, pos)?;
+                return Ok(self.offset);
+            }
         };
 
-        r.rewind();
+        Seek::seek(r, pos)
+    }
+
+    fn rewind(&mut self) -> Result<(), SeekError> {
+        let r = match &mut self.reader {
+            Some(v) => v,
+            None => {
+                self.offset = 0;
+                return Ok(());
+            }
+        };
 
-        Ok(())
+        Seek::rewind(r)
     }
 
-    fn stream_position(&mut self) -> std::io::Result<u64> {
+    fn stream_position(&mut self) -> Result<u64, SeekError> {
         let r = match &mut self.reader {
             Some(v) => v,
-            None => return Ok(0),
+            None => return Ok(self.offset),
         };
 
-        Ok(r.stream_position())
+        Seek::stream_position(r)
     }
 }
 
-#[cfg(feature = "std")]
-impl<D: DiskPartition> std::io::Read for File<D> {
-    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+impl<D: DiskPartition> Read for File<D> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ReadError> {
         match &mut self.reader {
             Some(v) => v.read(buf),
             None => Ok(0),
@@ -139,9 +552,105 @@ impl<D: DiskPartition> std::io::Read for File<D> {
     }
 }
 
+#[cfg(feature = "std")]
+impl<D> std::io::Seek for File<D> {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        Ok(Seek::seek(self, pos.into())?)
+    }
+
+    fn rewind(&mut self) -> std::io::Result<()> {
+        Ok(Seek::rewind(self)?)
+    }
+
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        Ok(Seek::stream_position(self)?)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<D: DiskPartition> std::io::Read for File<D> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(Read::read(self, buf)?)
+    }
+}
+
 /// Represents an error for [`File::new()`].
 #[derive(Debug, Error)]
 pub enum NewError {
     #[error("cannot create a clusters reader for allocation {0}:{1}")]
     CreateClustersReaderFailed(usize, u64, #[source] crate::cluster::NewError),
 }
+
+/// Represents an error when the clusters of a [`File`] cannot be determined.
+#[derive(Debug, Error)]
+pub enum ClustersError {
+    #[error("cluster #{0} has an invalid FAT entry")]
+    CorruptClusterChain(usize),
+}
+
+/// Represents an error for [`File::set_timestamps()`].
+#[derive(Error)]
+pub enum SetTimestampsError<D: DiskPartition> {
+    #[error("location of the file entry is not valid")]
+    InvalidEntryLocation,
+
+    #[error("cannot write the data at {0:#x}")]
+    WriteFailed(u64, #[source] D::Err),
+}
+
+impl<D: DiskPartition> Debug for SetTimestampsError<D> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidEntryLocation => write!(f, "InvalidEntryLocation"),
+            Self::WriteFailed(arg0, arg1) => f
+                .debug_tuple("WriteFailed")
+                .field(arg0)
+                .field(arg1)
+                .finish(),
+        }
+    }
+}
+
+/// Represents an error for [`File::set_len()`].
+#[derive(Error)]
+pub enum SetLenError<D: DiskPartition> {
+    #[error("cluster #{0} has an invalid FAT entry")]
+    CorruptClusterChain(usize),
+
+    #[error("cannot allocate or free clusters")]
+    AllocateFailed(#[source] AllocError<D>),
+
+    #[error("cluster #{0} is not valid")]
+    InvalidCluster(usize),
+
+    #[error("location of the file entry is not valid")]
+    InvalidEntryLocation,
+
+    #[error("cannot write the data at {0:#x}")]
+    WriteFailed(u64, #[source] D::Err),
+
+    #[error("cannot create a clusters reader")]
+    CreateClustersReaderFailed(#[source] crate::cluster::NewError),
+}
+
+impl<D: DiskPartition> Debug for SetLenError<D> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::CorruptClusterChain(arg0) => {
+                f.debug_tuple("CorruptClusterChain").field(arg0).finish()
+            }
+            Self::AllocateFailed(arg0) => f.debug_tuple("AllocateFailed").field(arg0).finish(),
+            Self::InvalidCluster(arg0) => f.debug_tuple("InvalidCluster").field(arg0).finish(),
+            Self::InvalidEntryLocation => write!(f, "InvalidEntryLocation"),
+            Self::WriteFailed(arg0, arg1) => f
+                .debug_tuple("WriteFailed")
+                .field(arg0)
+                .field(arg1)
+                .finish(),
+            Self::CreateClustersReaderFailed(arg0) => f
+                .debug_tuple("CreateClustersReaderFailed")
+                .field(arg0)
+                .finish(),
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use crate::disk::{DiskPartition, WritableDiskPartition};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use byteorder::{ByteOrder, LE};
+use core::cmp::min;
+use core::fmt::Debug;
+use thiserror::Error;
+
+/// Writes an empty exFAT filesystem to `disk`.
+///
+/// Only the Main Boot Region, Backup Boot Region, FAT and the clusters used by Allocation Bitmap,
+/// Up-case Table and the root directory will be written. The rest of the Cluster Heap is left
+/// untouched.
+pub fn format<P: WritableDiskPartition>(
+    disk: &P,
+    opts: FormatOptions,
+) -> Result<(), FormatError<P>> {
+    // Check options.
+    let bytes_per_sector = opts.bytes_per_sector;
+
+    if !bytes_per_sector.is_power_of_two() || !(512..=4096).contains(&bytes_per_sector) {
+        return Err(FormatError::InvalidBytesPerSector);
+    }
+
+    let sectors_per_cluster = opts
+        .sectors_per_cluster
+        .unwrap_or_else(|| default_cluster_size(opts.size) / bytes_per_sector);
+
+    if !sectors_per_cluster.is_power_of_two() || sectors_per_cluster > 0x2000000 / bytes_per_sector
+    {
+        return Err(FormatError::InvalidSectorsPerCluster);
+    }
+
+    let label: Vec<u16> = opts.volume_label.encode_utf16().collect();
+
+    if label.len() > 11 {
+        return Err(FormatError::InvalidVolumeLabel);
+    }
+
+    // Calculate layout. The Cluster Heap is aligned to the cluster boundary.
+    let volume_length = opts.size / bytes_per_sector;
+    let fat_offset = 24u64.next_multiple_of(sectors_per_cluster);
+    let max_clusters = volume_length.saturating_sub(fat_offset) / sectors_per_cluster;
+    let fat_length = ((max_clusters + 2) * 4).div_ceil(bytes_per_sector);
+    let cluster_heap_offset = (fat_offset + fat_length).next_multiple_of(sectors_per_cluster);
+    let cluster_count = volume_length.saturating_sub(cluster_heap_offset) / sectors_per_cluster;
+
+    if cluster_count > 0xfffffff5 {
+        return Err(FormatError::VolumeTooLarge);
+    }
+
+    // Allocate clusters for Allocation Bitmap, Up-case Table and the root directory.
+    let cluster_size = bytes_per_sector * sectors_per_cluster;
+    let bitmap_length = cluster_count.div_ceil(8);
+    let upcase = upcase_table();
+    let bitmap_clusters = bitmap_length.div_ceil(cluster_size);
+    let upcase_clusters = (upcase.len() as u64).div_ceil(cluster_size);
+    let bitmap_cluster = 2;
+    let upcase_cluster = bitmap_cluster + bitmap_clusters;
+    let root_cluster = upcase_cluster + upcase_clusters;
+    let used = bitmap_clusters + upcase_clusters + 1;
+
+    if cluster_count < used {
+        return Err(FormatError::VolumeTooSmall);
+    }
+
+    // Build Allocation Bitmap.
+    let mut bitmap = vec![0u8; bitmap_length as usize];
+
+    for i in 0..(used as usize) {
+        bitmap[i / 8] |= 1 << (i % 8);
+    }
+
+    // Build the root directory.
+    let mut root = vec![0u8; cluster_size as usize];
+    let entry = &mut root[..32];
+
+    entry[0] = 0x81;
+    LE::write_u32(&mut entry[20..], bitmap_cluster as u32);
+    LE::write_u64(&mut entry[24..], bitmap_length);
+
+    let checksum = upcase
+        .iter()
+        .fold(0u32, |c, &b| c.rotate_right(1).wrapping_add(b.into()));
+    let entry = &mut root[32..64];
+
+    entry[0] = 0x82;
+    LE::write_u32(&mut entry[4..], checksum);
+    LE::write_u32(&mut entry[20..], upcase_cluster as u32);
+    LE::write_u64(&mut entry[24..], upcase.len() as u64);
+
+    if !label.is_empty() {
+        let entry = &mut root[64..96];
+
+        entry[0] = 0x83;
+        entry[1] = label.len() as u8;
+        LE::write_u16_into(&label, &mut entry[2..(2 + label.len() * 2)]);
+    }
+
+    // Build Boot Sector.
+    let mut boot = vec![0u8; bytes_per_sector as usize * 12];
+    let sector = &mut boot[..512];
+
+    sector[..3].copy_from_slice(&[0xeb, 0x76, 0x90]);
+    sector[3..11].copy_from_slice(b"EXFAT   ");
+    LE::write_u64(&mut sector[72..], volume_length);
+    LE::write_u32(&mut sector[80..], fat_offset as u32);
+    LE::write_u32(&mut sector[84..], fat_length as u32);
+    LE::write_u32(&mut sector[88..], cluster_heap_offset as u32);
+    LE::write_u32(&mut sector[92..], cluster_count as u32);
+    LE::write_u32(&mut sector[96..], root_cluster as u32);
+    LE::write_u32(&mut sector[100..], opts.serial_number);
+    LE::write_u16(&mut sector[104..], 0x0100);
+    sector[108] = bytes_per_sector.trailing_zeros() as u8;
+    sector[109] = sectors_per_cluster.trailing_zeros() as u8;
+    sector[110] = 1;
+    sector[111] = 0x80;
+    sector[112] = (used * 100 / cluster_count) as u8;
+    sector[120..510].fill(0xf4);
+    sector[510..].copy_from_slice(&[0x55, 0xaa]);
+
+    // Build Extended Boot Sectors.
+    let bps = bytes_per_sector as usize;
+
+    for i in 1..9 {
+        boot[(i * bps + bps - 4)..((i + 1) * bps)].copy_from_slice(&[0, 0, 0x55, 0xaa]);
+    }
+
+    // Build Boot Checksum.
+    let checksum = boot[..(bps * 11)]
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != 106 && i != 107 && i != 112)
+        .fold(0u32, |c, (_, &b)| c.rotate_right(1).wrapping_add(b.into()));
+
+    for v in boot[(bps * 11)..].chunks_mut(4) {
+        LE::write_u32(v, checksum);
+    }
+
+    // Write Main and Backup Boot Region.
+    let region = bytes_per_sector * 12;
+
+    for offset in [0, region] {
+        if let Err(e) = disk.write_all(offset, &boot) {
+            return Err(FormatError::WriteFailed(offset, e));
+        }
+    }
+
+    // Write FAT.
+    let offset = fat_offset * bytes_per_sector;
+    let length = fat_length * bytes_per_sector;
+
+    zero(disk, offset, length)?;
+
+    let mut fat = Vec::with_capacity((used as usize + 2) * 4);
+    let chains = [
+        (bitmap_cluster, bitmap_clusters),
+        (upcase_cluster, upcase_clusters),
+        (root_cluster, 1),
+    ];
+
+    fat.extend_from_slice(&0xfffffff8u32.to_le_bytes());
+    fat.extend_from_slice(&0xffffffffu32.to_le_bytes());
+
+    for (first, count) in chains {
+        for c in first..(first + count) {
+            let next = if c == first + count - 1 {
+                0xffffffff
+            } else {
+                c as u32 + 1
+            };
+
+            fat.extend_from_slice(&next.to_le_bytes());
+        }
+    }
+
+    if let Err(e) = disk.write_all(offset, &fat) {
+        return Err(FormatError::WriteFailed(offset, e));
+    }
+
+    // Write the clusters.
+    let cluster_offset =
+        |c: u64| (cluster_heap_offset + (c - 2) * sectors_per_cluster) * bytes_per_sector;
+    let data = [
+        (bitmap_cluster, bitmap_clusters, &bitmap[..]),
+        (upcase_cluster, upcase_clusters, &upcase[..]),
+        (root_cluster, 1, &root[..]),
+    ];
+
+    for (first, count, data) in data {
+        let offset = cluster_offset(first);
+
+        if let Err(e) = disk.write_all(offset, data) {
+            return Err(FormatError::WriteFailed(offset, e));
+        }
+
+        let len = data.len() as u64;
+
+        zero(disk, offset + len, count * cluster_size - len)?;
+    }
+
+    Ok(())
+}
+
+/// Gets the default cluster size for a volume of `size` bytes.
+fn default_cluster_size(size: u64) -> u64 {
+    if size <= 256 * 1024 * 1024 {
+        4096
+    } else if size <= 32 * 1024 * 1024 * 1024 {
+        32768
+    } else {
+        131072
+    }
+}
+
+/// Builds a compressed Up-case Table from the Unicode data of the standard library.
+fn upcase_table() -> Vec<u8> {
+    fn flush(table: &mut Vec<u16>, start: usize, count: usize) {
+        // A range of less than 3 characters is smaller when it is not compressed.
+        if count < 3 {
+            table.extend((start..(start + count)).map(|c| c as u16));
+        } else {
+            table.extend([0xffff, count as u16]);
+        }
+    }
+
+    let mut table: Vec<u16> = Vec::new();
+    let mut identity = 0usize;
+
+    for c in 0..0x10000usize {
+        let mut upper = char::from_u32(c as u32).map(|v| v.to_uppercase());
+        let mapped = match upper.as_mut().map(|v| (v.next(), v.next())) {
+            Some((Some(v), None)) if (v as u32) < 0x10000 => v as usize,
+            _ => c,
+        };
+
+        if mapped == c {
+            identity += 1;
+            continue;
+        }
+
+        flush(&mut table, c - identity, identity);
+        table.push(mapped as u16);
+        identity = 0;
+    }
+
+    flush(&mut table, 0x10000 - identity, identity);
+
+    let mut data = vec![0u8; table.len() * 2];
+
+    LE::write_u16_into(&table, &mut data);
+
+    data
+}
+
+/// Writes `len` zeros starting at `offset`.
+fn zero<P: WritableDiskPartition>(
+    disk: &P,
+    mut offset: u64,
+    len: u64,
+) -> Result<(), FormatError<P>> {
+    let zeros = vec![0u8; min(len, 0x100000) as usize];
+    let end = offset + len;
+
+    while offset < end {
+        let n = min(end - offset, zeros.len() as u64) as usize;
+
+        if let Err(e) = disk.write_all(offset, &zeros[..n]) {
+            return Err(FormatError::WriteFailed(offset, e));
+        }
+
+        offset += n as u64;
+    }
+
+    Ok(())
+}
+
+/// Options for [`format()`].
+pub struct FormatOptions {
+    size: u64,
+    bytes_per_sector: u64,
+    sectors_per_cluster: Option<u64>,
+    volume_label: String,
+    serial_number: u32,
+}
+
+impl FormatOptions {
+    /// Creates a new [`FormatOptions`] for a volume of `size` bytes.
+    ///
+    /// The sector size will be 512 bytes and the cluster size will be chosen from `size`.
+    pub fn new(size: u64) -> Self {
+        Self {
+            size,
+            bytes_per_sector: 512,
+            sectors_per_cluster: None,
+            volume_label: String::new(),
+            serial_number: 0,
+        }
+    }
+
+    /// Sets the size of a sector, in bytes. This must be a power of two between 512 and 4096.
+    pub fn bytes_per_sector(mut self, v: u64) -> Self {
+        self.bytes_per_sector = v;
+        self
+    }
+
+    /// Sets the number of sectors in a cluster. This must be a power of two.
+    pub fn sectors_per_cluster(mut self, v: u64) -> Self {
+        self.sectors_per_cluster = Some(v);
+        self
+    }
+
+    /// Sets the volume label. The label must not be longer than 11 UTF-16 code units.
+    pub fn volume_label(mut self, v: impl Into<String>) -> Self {
+        self.volume_label = v.into();
+        self
+    }
+
+    /// Sets VolumeSerialNumber.
+    pub fn serial_number(mut self, v: u32) -> Self {
+        self.serial_number = v;
+        self
+    }
+}
+
+/// Represents an error when [`format()`] fails.
+#[derive(Error)]
+pub enum FormatError<P: DiskPartition> {
+    #[error("invalid bytes per sector")]
+    InvalidBytesPerSector,
+
+    #[error("invalid sectors per cluster")]
+    InvalidSectorsPerCluster,
+
+    #[error("invalid volume label")]
+    InvalidVolumeLabel,
+
+    #[error("the volume is too small")]
+    VolumeTooSmall,
+
+    #[error("the volume is too large")]
+    VolumeTooLarge,
+
+    #[error("cannot write the data at {0:#x}")]
+    WriteFailed(u64, #[source] P::Err),
+}
+
+impl<P: DiskPartition> Debug for FormatError<P> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidBytesPerSector => write!(f, "InvalidBytesPerSector"),
+            Self::InvalidSectorsPerCluster => write!(f, "InvalidSectorsPerCluster"),
+            Self::InvalidVolumeLabel => write!(f, "InvalidVolumeLabel"),
+            Self::VolumeTooSmall => write!(f, "VolumeTooSmall"),
+            Self::VolumeTooLarge => write!(f, "VolumeTooLarge"),
+            Self::WriteFailed(arg0, arg1) => f
+                .debug_tuple("WriteFailed")
+                .field(arg0)
+                .field(arg1)
+                .finish(),
+        }
+    }
+}
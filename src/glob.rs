@@ -0,0 +1,122 @@
+use crate::disk::DiskPartition;
+use crate::file::File;
+use crate::{Directory, DirectoryError, Item, Root};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+impl<P: DiskPartition> Root<P> {
+    /// Returns an iterator over all files with the path matched `pattern`.
+    ///
+    /// The pattern is matched against the path relative to the root directory (e.g. `dir1/file2`)
+    /// with `*` matches any characters except `/`, `?` matches one character and `**` matches any
+    /// number of directories. The match is case-insensitive according to the Up-case Table. Each
+    /// item is the absolute path of the file and the file itself.
+    pub fn glob(&self, pattern: &str) -> Glob<'_, P> {
+        let pattern = self.to_uppercase(pattern.trim_start_matches('/'));
+
+        Glob {
+            root: self,
+            pattern,
+            dirs: alloc::vec![self.root.clone()],
+            items: Vec::new(),
+        }
+    }
+}
+
+/// An iterator over the files that match a pattern.
+///
+/// This struct is created by [`Root::glob()`].
+pub struct Glob<'a, P: DiskPartition> {
+    root: &'a Root<P>,
+    pattern: String,
+    dirs: Vec<Directory<P>>,
+    items: Vec<(String, Item<P>)>,
+}
+
+impl<P: DiskPartition> Iterator for Glob<'_, P> {
+    type Item = Result<(String, File<P>), DirectoryError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Check the items of the current directory.
+            while let Some((path, item)) = self.items.pop() {
+                match item {
+                    Item::Directory(d) => self.dirs.push(d),
+                    Item::File(f) => {
+                        let name = self.root.to_uppercase(&path[1..]);
+
+                        if matches(&self.pattern, &name) {
+                            return Some(Ok((path, f)));
+                        }
+                    }
+                }
+            }
+
+            // Move to the next directory.
+            let dir = self.dirs.pop()?;
+            let items = match dir.open() {
+                Ok(v) => v,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let parent = dir.path().trim_end_matches('/');
+
+            self.items.extend(
+                items
+                    .into_iter()
+                    .rev()
+                    .map(|i| (alloc::format!("{}/{}", parent, i.name()), i)),
+            );
+        }
+    }
+}
+
+/// Returns `true` if `path` matches `pattern`. Both of them must be already up-cased.
+fn matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+
+    matches_components(&pattern, &path)
+}
+
+fn matches_components(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        Some((&"**", rest)) => (0..=path.len()).any(|i| matches_components(rest, &path[i..])),
+        Some((first, rest)) => match path.split_first() {
+            Some((name, path)) => matches_name(first, name) && matches_components(rest, path),
+            None => false,
+        },
+        None => path.is_empty(),
+    }
+}
+
+fn matches_name(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    // Use backtracking to the last star, which is linear for most patterns.
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    star = Some((sp, sn + 1));
+                    p = sp + 1;
+                    n = sn + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
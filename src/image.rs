@@ -0,0 +1,401 @@
+use crate::disk::{DiskPartition, WritableDiskPartition};
+use std::cell::RefCell;
+use std::cmp::min;
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::sync::{Mutex, RwLock};
+
+/// An implementation of [`DiskPartition`] for [`Read`] and [`Seek`].
+///
+/// [`WritableDiskPartition`] is also implemented if the underlying object implements [`Write`].
+///
+/// All reads are serialized with a mutex since each read requires a seek on the underlying
+/// object.
+pub struct Image<F> {
+    file: Mutex<F>,
+    offset: u64,
+    len: Option<u64>,
+}
+
+impl<F: Seek> Image<F> {
+    /// Create a new [`Image`] with the partition begins at the current position of `file`.
+    pub fn open(mut file: F) -> Result<Self, Error> {
+        let offset = file.stream_position()?;
+
+        Ok(Self::open_at(file, offset))
+    }
+
+    /// Create a new [`Image`] with the partition begins at `partition_offset` of `file`.
+    ///
+    /// This can be used to open an exFAT partition inside a full-disk image (e.g. `/dev/sda`).
+    ///
+    /// The size of the partition is determined here by seeking to the end of `file`.
+    pub fn open_at(mut file: F, partition_offset: u64) -> Self {
+        let len = file
+            .seek(SeekFrom::End(0))
+            .ok()
+            .map(|v| v.saturating_sub(partition_offset));
+
+        Self {
+            file: Mutex::new(file),
+            offset: partition_offset,
+            len,
+        }
+    }
+}
+
+impl<F> Image<F> {
+    /// Gets the offset of the partition in the underlying object, in bytes.
+    pub fn partition_offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> F {
+        self.file.into_inner().unwrap()
+    }
+}
+
+impl<F: Read + Seek> DiskPartition for Image<F> {
+    type Err = Error;
+
+    fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Err> {
+        let offset = match self.offset.checked_add(offset) {
+            Some(v) => v,
+            None => return Err(Error::from(ErrorKind::InvalidInput)),
+        };
+
+        let mut file = self.file.lock().unwrap();
+
+        file.seek(SeekFrom::Start(offset))?;
+        file.read(buf)
+    }
+
+    /// The size that was determined when this [`Image`] was opened.
+    fn len(&self) -> Option<u64> {
+        self.len
+    }
+}
+
+impl<F: Read + Write + Seek> WritableDiskPartition for Image<F> {
+    fn write(&self, offset: u64, buf: &[u8]) -> Result<usize, Self::Err> {
+        let offset = match self.offset.checked_add(offset) {
+            Some(v) => v,
+            None => return Err(Error::from(ErrorKind::InvalidInput)),
+        };
+
+        let mut file = self.file.lock().unwrap();
+
+        file.seek(SeekFrom::Start(offset))?;
+        file.write(buf)
+    }
+}
+
+/// Same as [`Image`] but uses [`RefCell`] instead of a mutex.
+///
+/// This avoids locking the mutex on every read but the resulting [`Root`][crate::Root] and all of
+/// the objects created from it cannot be shared between threads. Use [`Image`] if you need that.
+pub struct SeekImage<F> {
+    file: RefCell<F>,
+    offset: u64,
+    len: Option<u64>,
+}
+
+impl<F: Seek> SeekImage<F> {
+    /// Create a new [`SeekImage`] with the partition begins at the current position of `file`.
+    pub fn open(mut file: F) -> Result<Self, Error> {
+        let offset = file.stream_position()?;
+
+        Ok(Self::open_at(file, offset))
+    }
+
+    /// Create a new [`SeekImage`] with the partition begins at `partition_offset` of `file`.
+    ///
+    /// The size of the partition is determined here by seeking to the end of `file`.
+    pub fn open_at(mut file: F, partition_offset: u64) -> Self {
+        let len = file
+            .seek(SeekFrom::End(0))
+            .ok()
+            .map(|v| v.saturating_sub(partition_offset));
+
+        Self {
+            file: RefCell::new(file),
+            offset: partition_offset,
+            len,
+        }
+    }
+}
+
+impl<F> SeekImage<F> {
+    /// Gets the offset of the partition in the underlying object, in bytes.
+    pub fn partition_offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> F {
+        self.file.into_inner()
+    }
+}
+
+impl<F: Read + Seek> DiskPartition for SeekImage<F> {
+    type Err = Error;
+
+    fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Err> {
+        let offset = match self.offset.checked_add(offset) {
+            Some(v) => v,
+            None => return Err(Error::from(ErrorKind::InvalidInput)),
+        };
+
+        let mut file = self.file.borrow_mut();
+
+        file.seek(SeekFrom::Start(offset))?;
+        file.read(buf)
+    }
+
+    /// The size that was determined when this [`SeekImage`] was opened.
+    fn len(&self) -> Option<u64> {
+        self.len
+    }
+}
+
+impl<F: Read + Write + Seek> WritableDiskPartition for SeekImage<F> {
+    fn write(&self, offset: u64, buf: &[u8]) -> Result<usize, Self::Err> {
+        let offset = match self.offset.checked_add(offset) {
+            Some(v) => v,
+            None => return Err(Error::from(ErrorKind::InvalidInput)),
+        };
+
+        let mut file = self.file.borrow_mut();
+
+        file.seek(SeekFrom::Start(offset))?;
+        file.write(buf)
+    }
+}
+
+/// Maximum size of the internal buffer of [`AlignedImage`] when reading unaligned memory.
+const MAX_BOUNCE_SIZE: usize = 0x100000;
+
+/// An implementation of [`DiskPartition`] for a device that requires aligned reads (e.g. a block
+/// device opened with `O_DIRECT`).
+///
+/// The whole blocks of the requested range are read at once, directly into the caller's buffer if
+/// it is also aligned in memory. The unaligned head and tail are read as a whole block of the
+/// specified alignment into an internal buffer, which is also aligned, then copied out. Writing is
+/// not supported.
+pub struct AlignedImage<F> {
+    file: Mutex<(F, Vec<u8>)>,
+    alignment: usize,
+    offset: u64,
+    len: Option<u64>,
+}
+
+impl<F: Seek> AlignedImage<F> {
+    /// Create a new [`AlignedImage`] with the partition begins at the current position of `file`.
+    pub fn open(mut file: F, alignment: usize) -> Result<Self, Error> {
+        let offset = file.stream_position()?;
+
+        Self::open_at(file, offset, alignment)
+    }
+
+    /// Create a new [`AlignedImage`] with the partition begins at `partition_offset` of `file`.
+    ///
+    /// `alignment` must be a power of two, which usually is the sector size of the device.
+    pub fn open_at(mut file: F, partition_offset: u64, alignment: usize) -> Result<Self, Error> {
+        if !alignment.is_power_of_two() {
+            return Err(Error::from(ErrorKind::InvalidInput));
+        }
+
+        let len = file
+            .seek(SeekFrom::End(0))
+            .ok()
+            .map(|v| v.saturating_sub(partition_offset));
+
+        Ok(Self {
+            file: Mutex::new((file, Vec::new())),
+            alignment,
+            offset: partition_offset,
+            len,
+        })
+    }
+}
+
+impl<F> AlignedImage<F> {
+    /// Gets the offset of the partition in the underlying object, in bytes.
+    pub fn partition_offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn alignment(&self) -> usize {
+        self.alignment
+    }
+
+    pub fn into_inner(self) -> F {
+        self.file.into_inner().unwrap().0
+    }
+}
+
+impl<F: Read + Seek> AlignedImage<F> {
+    /// Reads `len` bytes at `pos` into an aligned region of `buffer` and returns the bytes that
+    /// was read. Both `pos` and `len` must be a multiple of `alignment`.
+    fn read_bounce<'a>(
+        file: &mut F,
+        buffer: &'a mut Vec<u8>,
+        pos: u64,
+        len: usize,
+        alignment: usize,
+    ) -> Result<&'a [u8], Error> {
+        // Allocate one more block so we can find an aligned region inside it.
+        if buffer.len() < len + alignment {
+            *buffer = vec![0u8; len + alignment];
+        }
+
+        let start = buffer.as_ptr().align_offset(alignment);
+        let block = &mut buffer[start..(start + len)];
+
+        file.seek(SeekFrom::Start(pos))?;
+
+        let read = file.read(block)?;
+
+        Ok(&block[..read])
+    }
+}
+
+impl<F: Read + Seek> DiskPartition for AlignedImage<F> {
+    type Err = Error;
+
+    fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Err> {
+        let offset = match self.offset.checked_add(offset) {
+            Some(v) => v,
+            None => return Err(Error::from(ErrorKind::InvalidInput)),
+        };
+
+        let alignment = self.alignment;
+        let skip = (offset % alignment as u64) as usize;
+        let mut file = self.file.lock().unwrap();
+        let (file, buffer) = &mut *file;
+        let mut done = 0;
+
+        // Read the unaligned head through the internal buffer.
+        if skip != 0 {
+            let block =
+                Self::read_bounce(file, buffer, offset - skip as u64, alignment, alignment)?;
+
+            if block.len() <= skip {
+                return Ok(0);
+            }
+
+            done = min(buf.len(), block.len() - skip);
+            buf[..done].copy_from_slice(&block[skip..(skip + done)]);
+
+            if done == buf.len() || block.len() != alignment {
+                return Ok(done);
+            }
+        }
+
+        // Read the aligned blocks in between at once. This goes directly into the caller's buffer
+        // if it is also aligned in memory.
+        let aligned = (buf.len() - done) & !(alignment - 1);
+
+        if aligned != 0 {
+            let dst = &mut buf[done..(done + aligned)];
+            let pos = offset + done as u64;
+            let read = if dst.as_ptr().align_offset(alignment) == 0 {
+                file.seek(SeekFrom::Start(pos))?;
+                file.read(dst)?
+            } else {
+                let len = min(aligned, MAX_BOUNCE_SIZE.max(alignment));
+                let block = Self::read_bounce(file, buffer, pos, len, alignment)?;
+
+                dst[..block.len()].copy_from_slice(block);
+                block.len()
+            };
+
+            done += read;
+
+            if read != aligned {
+                return Ok(done);
+            }
+        }
+
+        // Read the unaligned tail through the internal buffer.
+        if done != buf.len() {
+            let pos = offset + done as u64;
+            let block = Self::read_bounce(file, buffer, pos, alignment, alignment)?;
+            let len = min(buf.len() - done, block.len());
+
+            buf[done..(done + len)].copy_from_slice(&block[..len]);
+            done += len;
+        }
+
+        Ok(done)
+    }
+
+    /// The size that was determined when this [`AlignedImage`] was opened.
+    fn len(&self) -> Option<u64> {
+        self.len
+    }
+}
+
+/// An in-memory implementation of [`DiskPartition`] and [`WritableDiskPartition`].
+///
+/// Writing past the end will grow the data with zeros. This is mostly useful for tests.
+#[derive(Default)]
+pub struct MemoryImage {
+    data: RwLock<Vec<u8>>,
+}
+
+impl MemoryImage {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data: RwLock::new(data),
+        }
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.data.into_inner().unwrap()
+    }
+}
+
+impl DiskPartition for MemoryImage {
+    type Err = Error;
+
+    fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Err> {
+        let data = self.data.read().unwrap();
+        let data = match usize::try_from(offset).ok().and_then(|i| data.get(i..)) {
+            Some(v) => v,
+            None => return Ok(0),
+        };
+
+        let len = min(buf.len(), data.len());
+
+        buf[..len].copy_from_slice(&data[..len]);
+
+        Ok(len)
+    }
+
+    fn len(&self) -> Option<u64> {
+        Some(self.data.read().unwrap().len() as u64)
+    }
+}
+
+impl WritableDiskPartition for MemoryImage {
+    fn write(&self, offset: u64, buf: &[u8]) -> Result<usize, Self::Err> {
+        let start = match usize::try_from(offset) {
+            Ok(v) => v,
+            Err(_) => return Err(Error::from(ErrorKind::InvalidInput)),
+        };
+
+        let end = match start.checked_add(buf.len()) {
+            Some(v) => v,
+            None => return Err(Error::from(ErrorKind::InvalidInput)),
+        };
+
+        let mut data = self.data.write().unwrap();
+
+        if data.len() < end {
+            data.resize(end, 0);
+        }
+
+        data[start..end].copy_from_slice(buf);
+
+        Ok(buf.len())
+    }
+}
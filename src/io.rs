@@ -0,0 +1,118 @@
+//! Minimal I/O traits that work without `std`.
+//!
+//! When the `std` feature is enabled the types implementing these traits also implement
+//! [`std::io::Read`] and [`std::io::Seek`].
+use alloc::boxed::Box;
+use core::error::Error;
+use thiserror::Error;
+
+/// An analog of `std::io::Read`.
+pub trait Read {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ReadError>;
+
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<(), ReadError> {
+        while !buf.is_empty() {
+            let n = self.read(buf)?;
+
+            if n == 0 {
+                return Err(ReadError::UnexpectedEof);
+            }
+
+            buf = &mut buf[n..];
+        }
+
+        Ok(())
+    }
+}
+
+/// An analog of `std::io::Seek`.
+pub trait Seek {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, SeekError>;
+
+    fn rewind(&mut self) -> Result<(), SeekError> {
+        self.seek(SeekFrom::Start(0))?;
+        Ok(())
+    }
+
+    fn stream_position(&mut self) -> Result<u64, SeekError> {
+        self.seek(SeekFrom::Current(0))
+    }
+}
+
+/// An analog of `std::io::SeekFrom`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(u64),
+    End(i64),
+    Current(i64),
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::SeekFrom> for SeekFrom {
+    fn from(value: std::io::SeekFrom) -> Self {
+        match value {
+            std::io::SeekFrom::Start(v) => Self::Start(v),
+            std::io::SeekFrom::End(v) => Self::End(v),
+            std::io::SeekFrom::Current(v) => Self::Current(v),
+        }
+    }
+}
+
+/// Represents an error for [`Read`].
+#[derive(Debug, Error)]
+pub enum ReadError {
+    #[error("cluster #{0} is not available")]
+    InvalidCluster(usize),
+
+    #[error("cannot read the data at {0:#x}")]
+    ReadFailed(u64, #[source] Box<dyn Error + Send + Sync>),
+
+    #[error("unexpected end of data")]
+    UnexpectedEof,
+}
+
+/// Same as [`ReadError`] but with the error from the partition as-is.
+#[derive(Debug, Error)]
+pub enum TypedReadError<E> {
+    #[error("cluster #{0} is not available")]
+    InvalidCluster(usize),
+
+    #[error("cannot read the data at {0:#x}")]
+    ReadFailed(u64, #[source] E),
+}
+
+impl<E: Error + Send + Sync + 'static> From<TypedReadError<E>> for ReadError {
+    fn from(value: TypedReadError<E>) -> Self {
+        match value {
+            TypedReadError::InvalidCluster(v) => Self::InvalidCluster(v),
+            TypedReadError::ReadFailed(o, e) => Self::ReadFailed(o, Box::new(e)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<ReadError> for std::io::Error {
+    fn from(value: ReadError) -> Self {
+        match value {
+            ReadError::UnexpectedEof => Self::from(std::io::ErrorKind::UnexpectedEof),
+            v => Self::other(v),
+        }
+    }
+}
+
+/// Represents an error for [`Seek`].
+#[derive(Debug, Error)]
+pub enum SeekError {
+    #[error("cannot seek before the beginning of the data")]
+    NegativeOffset,
+
+    #[error("cannot seek to {0:#x}")]
+    InvalidOffset(u64),
+}
+
+#[cfg(feature = "std")]
+impl From<SeekError> for std::io::Error {
+    fn from(value: SeekError) -> Self {
+        Self::new(std::io::ErrorKind::InvalidInput, value)
+    }
+}
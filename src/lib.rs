@@ -1,24 +1,60 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+pub use self::boot::*;
+pub use self::cluster::RawReader;
 pub use self::directory::*;
 pub use self::disk::*;
-
-use self::cluster::ClustersReader;
-use self::entries::{ClusterAllocation, EntriesReader, EntryType, FileEntry};
+#[cfg(feature = "std")]
+pub use self::extract::*;
+pub use self::format::*;
+#[cfg(feature = "glob")]
+pub use self::glob::*;
+pub use self::oem::*;
+pub use self::options::*;
+pub use self::partition::*;
+
+use self::bitmap::Bitmap;
+use self::cluster::{ClusterCache, ClustersReader};
+use self::entries::{ClusterAllocation, EntriesReader, EntryType, FileEntry, RawEntry};
 use self::fat::Fat;
 use self::file::File;
+use self::io::ReadError;
 use self::param::Params;
+use self::upcase::UpcaseTable;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
 use byteorder::{ByteOrder, LE};
-use core::fmt::Debug;
-use std::sync::Arc;
+use core::cmp::min;
+use core::fmt::{Debug, Display, Write};
+use core::hash::Hasher;
 use thiserror::Error;
 
+mod allocator;
+pub mod bitmap;
+mod boot;
 mod cluster;
+mod crc32;
 mod directory;
 mod disk;
-mod entries;
+pub mod entries;
+#[cfg(feature = "std")]
+mod extract;
 pub mod fat;
 pub mod file;
+mod format;
+#[cfg(feature = "glob")]
+mod glob;
+#[cfg(feature = "std")]
+pub mod image;
+pub mod io;
+mod oem;
+mod options;
 pub mod param;
+mod partition;
 pub mod timestamp;
+pub mod upcase;
 
 extern crate alloc;
 
@@ -26,24 +62,105 @@ extern crate alloc;
 ///
 /// This implementation follows the official specs
 /// https://learn.microsoft.com/en-us/windows/win32/fileio/exfat-specification.
+///
+/// All objects created from the same [`Root`] share the partition and the metadata of the volume.
+/// [`Root`], [`Directory`] and [`File`] are [`Send`] and [`Sync`] if the partition is so multiple
+/// files can be read from different threads at the same time.
 pub struct Root<P: DiskPartition> {
     volume_label: Option<String>,
+    volume_label_raw: Option<Vec<u16>>,
+    percent_in_use: u8,
+    used_backup_boot: bool,
+    oem_parameters: Option<OemParameters>,
+    boot_sector: BootSector,
+    disk: Arc<P>,
+    params: Arc<Params>,
+    fat: Arc<Fat>,
+    cache: Option<Arc<ClusterCache>>,
+    bitmap_alloc: ClusterAllocation,
+    upcase_alloc: ClusterAllocation,
+    root: Directory<P>,
     items: Vec<Item<P>>,
+    options: OpenOptions,
+    load_items: bool,
+}
+
+impl<P: DiskPartition> Root<ReadOnly<P>> {
+    /// Opens the root directory of `partition` without the ability to write.
+    ///
+    /// The methods that write to the volume will not be available on the returned [`Root`] even if
+    /// `partition` implements [`WritableDiskPartition`]. This is useful to inspect a fragile volume
+    /// (e.g. a volume with VolumeDirty set) without any risk of modifying it.
+    pub fn open_read_only(partition: P) -> Result<Self, RootError<ReadOnly<P>>> {
+        Self::open(ReadOnly::new(partition))
+    }
+
+    /// Same as [`Root::open_read_only()`] but with the specified options.
+    pub fn open_read_only_with(
+        partition: P,
+        options: OpenOptions,
+    ) -> Result<Self, RootError<ReadOnly<P>>> {
+        Self::open_with(ReadOnly::new(partition), options)
+    }
 }
 
 impl<P: DiskPartition> Root<P> {
     pub fn open(partition: P) -> Result<Self, RootError<P>> {
-        // Read boot sector.
-        let mut boot = [0u8; 512];
+        Self::open_with(partition, OpenOptions::new())
+    }
 
-        if let Err(e) = partition.read_exact(0, &mut boot) {
-            return Err(RootError::ReadMainBootFailed(e));
-        }
+    /// Opens the root directory of `partition` with the specified options.
+    pub fn open_with(partition: P, options: OpenOptions) -> Result<Self, RootError<P>> {
+        Self::load(Arc::new(partition), options, true)
+    }
 
-        // Check type.
-        if &boot[3..11] != b"EXFAT   " || !boot[11..64].iter().all(|&b| b == 0) {
-            return Err(RootError::NotExFat);
-        }
+    /// Opens `partition` without listing the root directory.
+    ///
+    /// Only the metadata of the volume will be loaded so this is faster than [`Root::open()`] on
+    /// a volume with a large root directory. The returned [`Root`] contains no items so iterating
+    /// it or [`Root::find_normalized()`] will not find anything. Use
+    /// [`Root::root_dir()`] to list the root directory.
+    pub fn open_lazy(partition: P) -> Result<Self, RootError<P>> {
+        Self::load(Arc::new(partition), OpenOptions::new(), false)
+    }
+
+    /// Opens the volume again from the same partition with the same options.
+    ///
+    /// This is useful when the content of the partition was changed by something else (e.g. the
+    /// media was re-inserted). The objects that were created from the previous [`Root`] will
+    /// continue to use the previous metadata.
+    pub fn reopen(self) -> Result<Self, RootError<P>> {
+        Self::load(self.disk, self.options, self.load_items)
+    }
+
+    fn load(disk: Arc<P>, options: OpenOptions, load_items: bool) -> Result<Self, RootError<P>> {
+        let partition: &P = &disk;
+
+        // Read boot region. Use the backup one if the main one is corrupted.
+        let skip_checksums = options.skip_checksums;
+        let (region, used_backup_boot) = match read_boot_region(partition, 0, skip_checksums) {
+            Ok(v) => (v, false),
+            Err(e @ (RootError::NotExFat | RootError::InvalidBootChecksum)) => {
+                // We don't know the sector size so we need to try all possible values.
+                let backup = (9..=12).find_map(|shift| {
+                    read_boot_region(partition, 12 << shift, skip_checksums)
+                        .ok()
+                        .filter(|r| r[108] == shift)
+                });
+
+                match backup {
+                    Some(v) => {
+                        options.warn(OpenWarning::UsedBackupBoot);
+                        (v, true)
+                    }
+                    None => return Err(e),
+                }
+            }
+            Err(e) => return Err(e),
+        };
+
+        let boot = &region[..512];
+        let boot_sector = BootSector::parse(boot);
 
         // Load fields.
         let params = Arc::new(Params {
@@ -84,32 +201,73 @@ impl<P: DiskPartition> Root<P> {
             },
         });
 
+        if params.volume_flags.volume_dirty() {
+            options.warn(OpenWarning::VolumeDirty);
+        }
+
+        if params.volume_flags.media_failure() {
+            options.warn(OpenWarning::MediaFailure);
+        }
+
+        if let Err(e) = params.validate() {
+            return Err(RootError::InvalidParams(e));
+        }
+
+        // Check if the partition can hold the whole volume so we don't need to read a large FAT
+        // from a truncated image.
+        if let Some(len) = partition.len() {
+            let sectors = params.cluster_count as u64 * params.sectors_per_cluster;
+            let required = params
+                .cluster_heap_offset
+                .saturating_add(sectors)
+                .saturating_mul(params.bytes_per_sector);
+
+            if len < required {
+                return Err(RootError::ImageTooSmall(required));
+            }
+        }
+
+        // Parse OEM Parameters.
+        let bytes_per_sector = params.bytes_per_sector as usize;
+        let oem_parameters =
+            OemParameters::parse(&region[(bytes_per_sector * 9)..(bytes_per_sector * 10)]);
+
         // Read FAT region.
         let active_fat = params.volume_flags.active_fat();
         let fat = if active_fat == 0 || params.number_of_fats == 2 {
-            match Fat::load(&params, &partition, active_fat) {
+            match Fat::load(&params, partition, active_fat) {
                 Ok(v) => Arc::new(v),
                 Err(e) => return Err(RootError::ReadFatRegionFailed(e)),
             }
         } else {
-            return Err(RootError::InvalidNumberOfFats);
+            return Err(RootError::InvalidActiveFat);
         };
 
         // Create a entries reader for the root directory.
-        let disk = Arc::new(partition);
+        let cache = match options.cache_size {
+            0 => None,
+            v => Some(Arc::new(ClusterCache::new(v))),
+        };
+
         let root_cluster = params.first_cluster_of_root_directory;
         let mut reader = match ClustersReader::new(&disk, &params, &fat, root_cluster, None, None) {
-            Ok(v) => EntriesReader::new(v),
+            Ok(mut v) => {
+                v.set_cache(cache.clone());
+
+                let mut reader = EntriesReader::new(v);
+
+                reader.set_skip_deleted(options.skip_deleted_entries);
+                reader
+            }
             Err(e) => return Err(RootError::CreateClustersReaderFailed(e)),
         };
 
         // Load root directory.
         let mut allocation_bitmaps: [Option<ClusterAllocation>; 2] = [None, None];
-        let mut upcase_table: Option<()> = None;
-        let mut volume_label: Option<String> = None;
-        let mut items: Vec<Item<P>> = Vec::new();
+        let mut upcase_table: Option<(ClusterAllocation, u32)> = None;
+        let mut volume_label: Option<Vec<u16>> = None;
 
-        loop {
+        while !reader.is_end() {
             // Read primary entry.
             let entry = match reader.read() {
                 Ok(v) => v,
@@ -119,7 +277,9 @@ impl<P: DiskPartition> Root<P> {
             // Check entry type.
             let ty = entry.ty();
 
-            if !ty.is_regular() {
+            if ty.is_empty_volume_label() {
+                continue;
+            } else if !ty.is_regular() {
                 break;
             } else if ty.type_category() != EntryType::PRIMARY {
                 return Err(RootError::NotPrimaryEntry(entry.index(), entry.cluster()));
@@ -163,15 +323,19 @@ impl<P: DiskPartition> Root<P> {
                     }
 
                     // Load fields.
-                    if let Err(e) = ClusterAllocation::load(&entry) {
-                        return Err(RootError::ReadClusterAllocationFailed(
-                            entry.index(),
-                            entry.cluster(),
-                            e,
-                        ));
-                    }
+                    let checksum = LE::read_u32(&entry.data()[4..]);
+                    let alloc = match ClusterAllocation::load(&entry) {
+                        Ok(v) => v,
+                        Err(e) => {
+                            return Err(RootError::ReadClusterAllocationFailed(
+                                entry.index(),
+                                entry.cluster(),
+                                e,
+                            ));
+                        }
+                    };
 
-                    upcase_table = Some(());
+                    upcase_table = Some((alloc, checksum));
                 }
                 (EntryType::CRITICAL, 3) => {
                     // Check if more than one volume label.
@@ -183,87 +347,773 @@ impl<P: DiskPartition> Root<P> {
                     let data = entry.data();
                     let character_count = data[1] as usize;
 
-                    if character_count > 11 {
+                    let character_count = if character_count <= 11 {
+                        character_count
+                    } else if options.lenient_volume_label {
+                        options.warn(OpenWarning::InvalidVolumeLabel);
+                        min(character_count, 15)
+                    } else {
                         return Err(RootError::InvalidVolumeLabel);
-                    }
+                    };
 
                     let raw_label = &data[2..(2 + character_count * 2)];
 
                     // Convert the label from little endian to native endian.
-                    let mut label = [0u16; 11];
-                    let label = &mut label[..character_count];
+                    let mut label = vec![0u16; character_count];
+
+                    LE::read_u16_into(raw_label, &mut label);
+
+                    if char::decode_utf16(label.iter().copied()).any(|c| c.is_err()) {
+                        if !options.lenient_volume_label {
+                            return Err(RootError::InvalidVolumeLabel);
+                        }
 
-                    LE::read_u16_into(raw_label, label);
+                        options.warn(OpenWarning::InvalidVolumeLabel);
+                    }
 
-                    volume_label = Some(String::from_utf16_lossy(label));
+                    volume_label = Some(label);
                 }
                 (EntryType::CRITICAL, 5) => {
-                    // Load the entry.
-                    let file = match FileEntry::load(&entry, &mut reader) {
-                        Ok(v) => v,
-                        Err(e) => return Err(RootError::LoadFileEntryFailed(e)),
-                    };
-
-                    let name = file.name;
-                    let attrs = file.attributes;
-                    let stream = file.stream;
-                    let timestamps = file.timestamps;
-
-                    // Add to the list.
-                    items.push(if attrs.is_directory() {
-                        Item::Directory(Directory::new(
-                            disk.clone(),
-                            params.clone(),
-                            fat.clone(),
-                            name,
-                            stream,
-                            timestamps,
-                        ))
-                    } else {
-                        match File::new(&disk, &params, &fat, name, stream, timestamps) {
-                            Ok(v) => Item::File(v),
-                            Err(e) => {
-                                return Err(RootError::CreateFileObjectFailed(
-                                    entry.index(),
-                                    entry.cluster(),
-                                    e,
-                                ));
-                            }
+                    // Skip secondary entries. The file will be loaded after we got the Up-case
+                    // Table since it is required to verify the name.
+                    for _ in 0..entry.data()[1] {
+                        if let Err(e) = reader.read() {
+                            return Err(RootError::ReadEntryFailed(e));
+                        }
+                    }
+                }
+                (importance, _)
+                    if importance == EntryType::BENIGN || options.ignore_unknown_entries =>
+                {
+                    // Skip secondary entries. Unknown benign entries can be safely skipped.
+                    options.warn(OpenWarning::UnknownEntrySkipped(
+                        entry.index(),
+                        entry.cluster(),
+                    ));
+
+                    for _ in 0..entry.data()[1] {
+                        if let Err(e) = reader.read() {
+                            return Err(RootError::ReadEntryFailed(e));
                         }
-                    });
+                    }
                 }
                 _ => return Err(RootError::UnknownEntry(entry.index(), entry.cluster())),
             }
         }
 
-        // Check allocation bitmap count.
+        // Check allocation bitmap count. TexFAT volumes have one bitmap for each FAT.
         if params.number_of_fats == 2 {
             if allocation_bitmaps[1].is_none() {
                 return Err(RootError::NoAllocationBitmap);
             }
+        } else if allocation_bitmaps[1].is_some() {
+            return Err(RootError::TooManyAllocationBitmap);
         } else if allocation_bitmaps[0].is_none() {
             return Err(RootError::NoAllocationBitmap);
         }
 
-        // Check Up-case Table.
-        if upcase_table.is_none() {
-            return Err(RootError::NoUpcaseTable);
+        // Load Allocation Bitmap that pairs with the active FAT.
+        let bitmap_alloc = match allocation_bitmaps[active_fat].take() {
+            Some(v) => v,
+            None => return Err(RootError::NoAllocationBitmap),
+        };
+        let inactive_bitmap = allocation_bitmaps[active_fat ^ 1].take();
+
+        let bitmap = match Bitmap::load(&disk, &params, &fat, &bitmap_alloc) {
+            Ok(v) => Arc::new(v),
+            Err(e) => return Err(RootError::LoadAllocationBitmapFailed(e)),
+        };
+
+        // Load Up-case Table.
+        let (upcase_alloc, upcase_checksum) = match upcase_table {
+            Some(v) => v,
+            None => return Err(RootError::NoUpcaseTable),
+        };
+
+        let upcase_checksum = if skip_checksums {
+            None
+        } else {
+            Some(upcase_checksum)
+        };
+
+        let upcase = match UpcaseTable::load(&disk, &params, &fat, &upcase_alloc, upcase_checksum) {
+            Ok(v) => Arc::new(v),
+            Err(e) => return Err(RootError::LoadUpcaseTableFailed(e)),
+        };
+
+        // Create a directory object for the root directory.
+        let len = fat.get_cluster_chain(root_cluster).count() as u64 * params.cluster_size();
+        let root = Directory::new(
+            disk.clone(),
+            params.clone(),
+            fat.clone(),
+            cache.clone(),
+            bitmap,
+            upcase,
+            Arc::new(options.clone()),
+            FileEntry::root(root_cluster, len),
+        );
+
+        // Load files. This is skipped when opening lazily.
+        let mut items: Vec<Item<P>> = Vec::new();
+
+        if load_items {
+            reader.rewind();
+
+            while !reader.is_end() {
+                // Read primary entry.
+                let entry = match reader.read() {
+                    Ok(v) => v,
+                    Err(e) => return Err(RootError::ReadEntryFailed(e)),
+                };
+
+                // Skip if not a file entry.
+                let ty = entry.ty();
+
+                if ty.is_empty_volume_label() {
+                    continue;
+                } else if !ty.is_regular() {
+                    break;
+                } else if ty.type_importance() != EntryType::CRITICAL || ty.type_code() != 5 {
+                    continue;
+                }
+
+                // Load the entry.
+                let file = match FileEntry::load(&entry, &mut reader, root.upcase(), &options) {
+                    Ok(v) => v,
+                    Err(e) => return Err(RootError::LoadFileEntryFailed(e)),
+                };
+
+                // Add to the list.
+                items.push(if file.attributes.is_directory() {
+                    Item::Directory(root.child(file))
+                } else {
+                    match root.new_file(file) {
+                        Ok(v) => Item::File(v),
+                        Err(e) => {
+                            return Err(RootError::CreateFileObjectFailed(
+                                entry.index(),
+                                entry.cluster(),
+                                e,
+                            ));
+                        }
+                    }
+                });
+            }
+        }
+
+        // Check cross-linked clusters.
+        if options.check_cross_links {
+            let mut used = vec![0u8; params.cluster_count.div_ceil(8)];
+            let metadata = [
+                bitmap_alloc.first_cluster(),
+                upcase_alloc.first_cluster(),
+                root_cluster,
+            ];
+            let clusters = metadata
+                .into_iter()
+                .chain(inactive_bitmap.map(|a| a.first_cluster()))
+                .flat_map(|c| fat.get_cluster_chain(c));
+
+            if let Some(c) = directory::mark_clusters(&mut used, clusters) {
+                return Err(RootError::CrossLinkedCluster(c));
+            }
+
+            match root.mark_clusters(&mut used) {
+                Ok(Some(c)) => return Err(RootError::CrossLinkedCluster(c)),
+                Ok(None) => {}
+                Err(e) => return Err(RootError::ReadDirectoryFailed(e)),
+            }
         }
 
         Ok(Self {
-            volume_label,
+            volume_label: volume_label.as_deref().map(String::from_utf16_lossy),
+            volume_label_raw: volume_label,
+            percent_in_use: boot[112],
+            used_backup_boot,
+            oem_parameters,
+            boot_sector,
+            disk,
+            params,
+            fat,
+            cache,
+            bitmap_alloc,
+            upcase_alloc,
+            root,
             items,
+            options,
+            load_items,
         })
     }
 
+    /// Returns a [`Directory`] for the root directory.
+    pub fn root_dir(&self) -> Directory<P> {
+        self.root.clone()
+    }
+
     pub fn volume_label(&self) -> Option<&str> {
         self.volume_label.as_deref()
     }
+
+    /// Returns the volume label as it is stored on the volume, without decoding.
+    pub fn volume_label_raw(&self) -> Option<&[u16]> {
+        self.volume_label_raw.as_deref()
+    }
+
+    /// Returns the percentage of allocated clusters in the Cluster Heap from the boot sector.
+    ///
+    /// This value is not guaranteed to be accurate. Returns [`None`] if the value is not available.
+    pub fn percent_in_use(&self) -> Option<u8> {
+        match self.percent_in_use {
+            v @ 0..=100 => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the volume was opened from the Backup Boot Region because the Main Boot
+    /// Region is corrupted.
+    pub fn used_backup_boot(&self) -> bool {
+        self.used_backup_boot
+    }
+
+    /// Finds an item named `name` in the root directory.
+    ///
+    /// Both `name` and the name of each item will be normalized to NFC before comparing them with
+    /// the Up-case Table. This allows a name from the system that uses NFD (e.g. macOS) to match.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn find_normalized(&self, name: &str) -> Option<&Item<P>> {
+        use unicode_normalization::UnicodeNormalization;
+
+        let name: String = name.nfc().collect();
+        let upcase = self.root.upcase();
+
+        self.items.iter().find(|i| {
+            let other: String = i.name().nfc().collect();
+            upcase.eq_ignore_case(&other, &name)
+        })
+    }
+
+    /// Returns the Flash Parameters from the OEM Parameters of the volume or [`None`] if not
+    /// present.
+    pub fn oem_parameters(&self) -> Option<OemParameters> {
+        self.oem_parameters
+    }
+
+    /// Returns the fields of the boot sector that was used to open the volume.
+    pub fn boot_sector(&self) -> BootSector {
+        self.boot_sector
+    }
+
+    /// Returns an iterator over all entries in the root directory, including Allocation Bitmap,
+    /// Up-case Table, Volume Label and the unused entries.
+    pub fn raw_entries(&self) -> Result<RawEntries<'_, P>, DirectoryError> {
+        self.root.raw_entries()
+    }
+
+    /// Returns the index of the FAT and Allocation Bitmap that is currently used (ActiveFat).
+    pub fn active_fat(&self) -> u8 {
+        self.params.volume_flags.active_fat() as u8
+    }
+
+    /// Returns the location and size of the regions on the volume.
+    pub fn geometry(&self) -> param::Geometry {
+        self.params.geometry()
+    }
+
+    /// Returns the number of FATs and Allocation Bitmaps on the volume (NumberOfFats).
+    pub fn number_of_fats(&self) -> u8 {
+        self.params.number_of_fats
+    }
+
+    /// Checks if all FATs on the volume are identical.
+    ///
+    /// This reads the inactive FAT from the partition every time it is called. Always success if
+    /// the volume has only one FAT.
+    pub fn verify_fats(&self) -> Result<(), VerifyFatsError<P>> {
+        if self.params.number_of_fats != 2 {
+            return Ok(());
+        }
+
+        // Load the inactive FAT.
+        let index = 1 - self.params.volume_flags.active_fat();
+        let other = match Fat::load(&self.params, self.disk.as_ref(), index) {
+            Ok(v) => v,
+            Err(e) => return Err(VerifyFatsError::LoadFatFailed(index, e)),
+        };
+
+        match self.fat.first_mismatch(&other) {
+            Some(i) => Err(VerifyFatsError::Mismatch(i)),
+            None => Ok(()),
+        }
+    }
+
+    /// Feeds the content of this volume into `hasher`, which can be used to verify a copy of the
+    /// volume.
+    ///
+    /// The free clusters are skipped according to the Allocation Bitmap so two copies that differ
+    /// only in the free clusters have the same hash. The data is fed one cluster at a time.
+    pub fn hash_volume<H: Hasher>(&self, hasher: &mut H, mode: HashMode) -> Result<(), ReadError> {
+        let params = &self.params;
+        let cluster_size = params.cluster_size();
+        let mut buf = vec![0u8; cluster_size as usize];
+        let mut feed = |offset: u64, len: u64| {
+            let end = offset + len;
+            let mut offset = offset;
+
+            while offset < end {
+                let buf = &mut buf[..(min(end - offset, cluster_size) as usize)];
+
+                if let Err(e) = self.disk.read_exact(offset, buf) {
+                    return Err(ReadError::ReadFailed(offset, Box::new(e)));
+                }
+
+                hasher.write(buf);
+                offset += buf.len() as u64;
+            }
+
+            Ok(())
+        };
+
+        // Feed Main Boot Region and FAT Region.
+        if mode == HashMode::Volume {
+            let bytes_per_sector = params.bytes_per_sector;
+            let fats = params.fat_length * u64::from(params.number_of_fats);
+
+            feed(0, bytes_per_sector * 12)?;
+            feed(
+                params.fat_offset * bytes_per_sector,
+                fats * bytes_per_sector,
+            )?;
+        }
+
+        // Feed the allocated clusters.
+        let bitmap = self.root.bitmap().snapshot();
+
+        for i in 0..params.cluster_count {
+            if bitmap[i / 8] & (1 << (i % 8)) == 0 {
+                continue;
+            }
+
+            match params.cluster_offset(i + 2) {
+                Some(v) => feed(v, cluster_size)?,
+                None => return Err(ReadError::InvalidCluster(i + 2)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Up-cases `name` with the Up-case Table of this volume.
+    ///
+    /// Two names are considered the same by exFAT if their up-cased values are equal.
+    pub fn to_uppercase(&self, name: &str) -> String {
+        self.root.upcase().to_uppercase(name)
+    }
+
+    /// Returns the location of the Up-case Table.
+    pub fn upcase_table_allocation(&self) -> &ClusterAllocation {
+        &self.upcase_alloc
+    }
+
+    /// Returns the location of the Allocation Bitmap for the active FAT.
+    pub fn allocation_bitmap_allocation(&self) -> &ClusterAllocation {
+        &self.bitmap_alloc
+    }
+
+    /// Returns a reader over the raw (compressed) data of the Up-case Table.
+    pub fn open_upcase_table(&self) -> Result<RawReader<P>, OpenMetadataError> {
+        self.open_metadata(&self.upcase_alloc)
+    }
+
+    /// Returns a reader over the raw data of the Allocation Bitmap for the active FAT.
+    pub fn open_allocation_bitmap(&self) -> Result<RawReader<P>, OpenMetadataError> {
+        self.open_metadata(&self.bitmap_alloc)
+    }
+
+    fn open_metadata(&self, alloc: &ClusterAllocation) -> Result<RawReader<P>, OpenMetadataError> {
+        let mut reader = match ClustersReader::new(
+            self.disk.clone(),
+            self.params.clone(),
+            &self.fat,
+            alloc.first_cluster(),
+            Some(alloc.data_length()),
+            None,
+        ) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(OpenMetadataError::CreateClustersReaderFailed(
+                    alloc.clone(),
+                    e,
+                ))
+            }
+        };
+
+        reader.set_cache(self.cache.clone());
+
+        Ok(RawReader::new(reader))
+    }
+
+    /// Reads the entry set at `index` on `cluster` from the partition, which is the location from
+    /// [`Item::entry_location()`].
+    ///
+    /// This is cheaper than listing the whole directory again to refresh a single item. The entry
+    /// set may continue on the next cluster of the directory, which is taken from the FAT or
+    /// `cluster + 1` if the FAT has no chain for `cluster` (e.g. the directory has NoFatChain set).
+    /// The cache is not used.
+    pub fn read_entry_at(&self, cluster: usize, index: usize) -> Result<Metadata, ReadEntryError> {
+        // Check the location.
+        let params = &self.params;
+        let cluster_size = params.cluster_size();
+
+        if params.cluster_offset(cluster).is_none() || index as u64 >= cluster_size / 32 {
+            return Err(ReadEntryError::InvalidLocation(cluster, index));
+        }
+
+        // An entry set is at most 19 entries so it will never span more than two clusters.
+        let (data_length, no_fat_chain) = match self.fat.get_valid_cluster_chain(cluster) {
+            Ok(_) => (None, None),
+            Err(_) => {
+                let count = min(2, params.cluster_count + 2 - cluster) as u64;
+                (Some(count * cluster_size), Some(true))
+            }
+        };
+
+        let mut reader = match ClustersReader::new(
+            &self.disk,
+            params,
+            &self.fat,
+            cluster,
+            data_length,
+            no_fat_chain,
+        ) {
+            Ok(v) => EntriesReader::new(v),
+            Err(e) => return Err(ReadEntryError::CreateClustersReaderFailed(e)),
+        };
+
+        reader.seek(index as u64 * 32);
+
+        // Load the entry set.
+        let entry = match reader.read() {
+            Ok(v) => v,
+            Err(e) => return Err(ReadEntryError::ReadEntryFailed(e)),
+        };
+
+        let ty = entry.ty();
+
+        if !ty.is_regular()
+            || ty.type_importance() != EntryType::CRITICAL
+            || ty.type_category() != EntryType::PRIMARY
+            || ty.type_code() != 5
+        {
+            return Err(ReadEntryError::NotFileEntry(index, cluster));
+        }
+
+        match FileEntry::load(&entry, &mut reader, self.root.upcase(), &self.options) {
+            Ok(v) => Ok(Metadata::new(&v)),
+            Err(e) => Err(ReadEntryError::LoadFileEntryFailed(e)),
+        }
+    }
+
+    /// Opens the file at `path` without consuming this [`Root`].
+    ///
+    /// The path is relative to the root directory with `/` as a separator.
+    pub fn open_file(&self, path: &str) -> Result<File<P>, OpenFileError> {
+        let (dirs, name) = self.resolve(path)?;
+        let parent = dirs.last().unwrap_or(&self.root);
+        let entry = match parent.scan(name, 0, &[]) {
+            Ok((Some(v), _)) => v,
+            Ok((None, _)) => return Err(OpenFileError::NotFound),
+            Err(e) => return Err(OpenFileError::ReadDirectoryFailed(e)),
+        };
+
+        if entry.attributes.is_directory() {
+            return Err(OpenFileError::IsDirectory);
+        }
+
+        match parent.new_file(entry) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(OpenFileError::CreateFileObjectFailed(e)),
+        }
+    }
+
+    /// Gets the file or directory at `path`.
+    ///
+    /// The path is relative to the root directory with `/` as a separator. An empty path refers to
+    /// the root directory.
+    pub fn lookup(&self, path: &str) -> Result<Item<P>, LookupError> {
+        let mut components = path.split('/').filter(|c| !c.is_empty()).peekable();
+        let mut dir = self.root.clone();
+
+        while let Some(c) = components.next() {
+            let item = match dir.find(c) {
+                Ok(Some(v)) => v,
+                Ok(None) => {
+                    return Err(LookupError::NotFound {
+                        component: String::from(c),
+                    })
+                }
+                Err(e) => return Err(LookupError::Io(e)),
+            };
+
+            match item {
+                Item::Directory(v) => dir = v,
+                Item::File(v) if components.peek().is_none() => return Ok(Item::File(v)),
+                Item::File(_) => {
+                    return Err(LookupError::NotADirectory {
+                        component: String::from(c),
+                    })
+                }
+            }
+        }
+
+        Ok(Item::Directory(dir))
+    }
+
+    /// Gets the directories leading to the last component of `path` and the last component.
+    fn resolve<'a>(&self, path: &'a str) -> Result<(Vec<Directory<P>>, &'a str), ResolveError> {
+        let mut components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+        let name = match components.pop() {
+            Some(v) => v,
+            None => return Err(ResolveError::InvalidPath),
+        };
+
+        let mut dirs: Vec<Directory<P>> = Vec::with_capacity(components.len());
+
+        for c in components {
+            let parent = dirs.last().unwrap_or(&self.root);
+            let entry = match parent.scan(c, 0, &[]) {
+                Ok((Some(v), _)) => v,
+                Ok((None, _)) => return Err(ResolveError::NotFound),
+                Err(e) => return Err(ResolveError::ReadDirectoryFailed(e)),
+            };
+
+            if !entry.attributes.is_directory() {
+                return Err(ResolveError::NotDirectory);
+            }
+
+            dirs.push(parent.child(entry));
+        }
+
+        Ok((dirs, name))
+    }
+}
+
+impl<P: WritableDiskPartition> Root<P> {
+    /// Sets the volume label. An empty `label` will remove the current label.
+    ///
+    /// The root directory will be extended by one cluster if there is no Volume Label entry and no
+    /// free entry.
+    pub fn set_volume_label(&mut self, label: &str) -> Result<(), SetVolumeLabelError<P>> {
+        let utf16: Vec<u16> = label.encode_utf16().collect();
+
+        if utf16.len() > 11 {
+            return Err(SetVolumeLabelError::TooLong);
+        }
+
+        // Find the current Volume Label entry.
+        let entries = match self.root.raw_entries() {
+            Ok(v) => v,
+            Err(e) => return Err(SetVolumeLabelError::ReadDirectoryFailed(e)),
+        };
+
+        let mut slot = None;
+
+        for entry in entries {
+            let entry = match entry {
+                Ok(v) => v,
+                Err(e) => return Err(SetVolumeLabelError::ReadEntryFailed(e)),
+            };
+
+            let ty = entry.ty();
+
+            if ty.is_end_of_directory() {
+                break;
+            } else if ty.is_empty_volume_label() || entry.data()[0] == 0x83 {
+                slot = Some(entry);
+                break;
+            }
+        }
+
+        // Use a free entry if there is no Volume Label entry.
+        let mut slot = match slot {
+            Some(v) => v,
+            None => {
+                let mut slots = match self.root.scan("", 1, &[]) {
+                    Ok((_, v)) => v,
+                    Err(e) => return Err(SetVolumeLabelError::ReadDirectoryFailed(e)),
+                };
+
+                if slots.is_empty() {
+                    if let Err(e) = self.root.extend(&mut slots, 1) {
+                        return Err(SetVolumeLabelError::UpdateDirectoryFailed(e));
+                    }
+                }
+
+                slots.remove(0)
+            }
+        };
+
+        // Write the entry.
+        slot.set_volume_label(&utf16);
+
+        if let Err(e) = self.root.write_entries(core::slice::from_ref(&slot)) {
+            return Err(SetVolumeLabelError::UpdateDirectoryFailed(e));
+        }
+
+        if utf16.is_empty() {
+            self.volume_label = None;
+            self.volume_label_raw = None;
+        } else {
+            self.volume_label = Some(label.into());
+            self.volume_label_raw = Some(utf16);
+        }
+
+        Ok(())
+    }
+
+    /// Renames or moves the file or directory at `from` to `to`.
+    ///
+    /// Both paths are relative to the root directory with `/` as a separator. The parent directory
+    /// of `to` must already exist.
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<(), RenameError<P>> {
+        // Resolve the paths.
+        let (from_dirs, from_name) = self.resolve(from)?;
+        let (mut to_dirs, to_name) = self.resolve(to)?;
+        let len = match directory::name_length(to_name) {
+            Some(v) => v,
+            None => return Err(RenameError::InvalidName),
+        };
+
+        // Find the source entry set.
+        let parent = from_dirs.last().unwrap_or(&self.root);
+        let source = match parent.scan(from_name, 0, &[]) {
+            Ok((Some(v), _)) => v,
+            Ok((None, _)) => return Err(RenameError::NotFound),
+            Err(e) => return Err(RenameError::ReadDirectoryFailed(e)),
+        };
+
+        let source_parent = parent.entry_location();
+
+        // Prevent moving a directory into itself.
+        let location = (source.cluster, source.index);
+
+        if source.attributes.is_directory()
+            && to_dirs.iter().any(|d| d.entry_location() == location)
+        {
+            return Err(RenameError::IntoSubtree);
+        }
+
+        // Find free entries on the destination. The source entries can be reused if both are in
+        // the same directory. The scan treats them as free so the source itself is never reported
+        // as an existing name, which allows changing only the case of the name.
+        let dest = to_dirs.last_mut().unwrap_or(&mut self.root);
+        let reuse = if dest.entry_location() == source_parent {
+            &source.entries[..]
+        } else {
+            &[]
+        };
+
+        let count = 2 + len.div_ceil(15);
+        let mut slots = match dest.scan(to_name, count, reuse) {
+            Ok((Some(_), _)) => return Err(RenameError::AlreadyExists),
+            Ok((None, v)) => v,
+            Err(e) => return Err(RenameError::ReadDirectoryFailed(e)),
+        };
+
+        if slots.len() < count {
+            if let Err(e) = dest.extend(&mut slots, count) {
+                return Err(RenameError::UpdateDirectoryFailed(e));
+            }
+        }
+
+        // Write the new entry set then delete the remaining entries of the old one.
+        let entry = source.with_name(slots, to_name, dest.upcase());
+        let deleted: Vec<RawEntry> = source
+            .entries
+            .iter()
+            .filter(|s| {
+                !entry
+                    .entries
+                    .iter()
+                    .any(|e| e.cluster() == s.cluster() && e.index() == s.index())
+            })
+            .map(|s| {
+                let mut s = s.clone();
+                s.delete();
+                s
+            })
+            .collect();
+
+        if let Err(e) = dest.write_entries(&entry.entries) {
+            return Err(RenameError::UpdateDirectoryFailed(e));
+        }
+
+        if let Err(e) = dest.write_entries(&deleted) {
+            return Err(RenameError::UpdateDirectoryFailed(e));
+        }
+
+        // Reload the items.
+        self.items = match self.root.open() {
+            Ok(v) => v,
+            Err(e) => return Err(RenameError::ReadDirectoryFailed(e)),
+        };
+
+        Ok(())
+    }
+}
+
+/// Reads the boot region at `offset` and verifies its checksum unless `skip_checksum` is `true`.
+fn read_boot_region<P: DiskPartition>(
+    partition: &P,
+    offset: u64,
+    skip_checksum: bool,
+) -> Result<Vec<u8>, RootError<P>> {
+    // Read boot sector.
+    let mut boot = [0u8; 512];
+
+    if let Err(e) = partition.read_exact(offset, &mut boot) {
+        return Err(RootError::ReadMainBootFailed(offset, e));
+    }
+
+    // Check type.
+    if &boot[3..11] != b"EXFAT   "
+        || !boot[11..64].iter().all(|&b| b == 0)
+        || boot[510..] != [0x55, 0xaa]
+    {
+        return Err(RootError::NotExFat);
+    }
+
+    // Read the whole region now that we know the sector size. The first 512 bytes only contain the
+    // fields we need to get here so the checksum must be computed on the full sectors.
+    let bytes_per_sector = match boot[108] {
+        v @ 9..=12 => 1usize << v,
+        _ => return Err(RootError::InvalidBytesPerSectorShift),
+    };
+
+    let mut region = vec![0u8; bytes_per_sector * 12];
+
+    if let Err(e) = partition.read_exact(offset, &mut region) {
+        return Err(RootError::ReadMainBootFailed(offset, e));
+    }
+
+    // Verify Boot Checksum. VolumeFlags and PercentInUse are excluded.
+    let (data, checksums) = region.split_at(bytes_per_sector * 11);
+    let checksum = data
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != 106 && i != 107 && i != 112)
+        .fold(0u32, |c, (_, &b)| c.rotate_right(1).wrapping_add(b.into()));
+
+    if !skip_checksum && !checksums.chunks(4).all(|v| LE::read_u32(v) == checksum) {
+        return Err(RootError::InvalidBootChecksum);
+    }
+
+    Ok(region)
 }
 
 impl<P: DiskPartition> IntoIterator for Root<P> {
     type Item = Item<P>;
-    type IntoIter = std::vec::IntoIter<Item<P>>;
+    type IntoIter = alloc::vec::IntoIter<Item<P>>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.items.into_iter()
@@ -271,41 +1121,318 @@ impl<P: DiskPartition> IntoIterator for Root<P> {
 }
 
 /// Represents FileAttributes in the File Directory Entry.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct FileAttributes(u16);
 
 impl FileAttributes {
+    pub const READ_ONLY: u16 = 0x0001;
+    pub const HIDDEN: u16 = 0x0002;
+    pub const SYSTEM: u16 = 0x0004;
+    pub const DIRECTORY: u16 = 0x0010;
+    pub const ARCHIVE: u16 = 0x0020;
+
+    const NAMES: [(u16, &'static str); 5] = [
+        (Self::READ_ONLY, "READ_ONLY"),
+        (Self::HIDDEN, "HIDDEN"),
+        (Self::SYSTEM, "SYSTEM"),
+        (Self::DIRECTORY, "DIRECTORY"),
+        (Self::ARCHIVE, "ARCHIVE"),
+    ];
+
+    /// Creates a [`FileAttributes`] from the raw value. Reserved bits are kept as is.
+    pub fn from_bits(v: u16) -> Self {
+        Self(v)
+    }
+
+    pub fn bits(self) -> u16 {
+        self.0
+    }
+
     pub fn is_read_only(self) -> bool {
-        (self.0 & 0x0001) != 0
+        (self.0 & Self::READ_ONLY) != 0
     }
 
     pub fn is_hidden(self) -> bool {
-        (self.0 & 0x0002) != 0
+        (self.0 & Self::HIDDEN) != 0
     }
 
     pub fn is_system(self) -> bool {
-        (self.0 & 0x0004) != 0
+        (self.0 & Self::SYSTEM) != 0
     }
 
     pub fn is_directory(self) -> bool {
-        (self.0 & 0x0010) != 0
+        (self.0 & Self::DIRECTORY) != 0
     }
 
     pub fn is_archive(self) -> bool {
-        (self.0 & 0x0020) != 0
+        (self.0 & Self::ARCHIVE) != 0
+    }
+}
+
+impl Debug for FileAttributes {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("FileAttributes(")?;
+
+        let mut first = true;
+        let mut sep = |f: &mut core::fmt::Formatter<'_>| {
+            if !core::mem::take(&mut first) {
+                f.write_str(" | ")?;
+            }
+
+            Ok(())
+        };
+
+        for (mask, name) in Self::NAMES {
+            if (self.0 & mask) != 0 {
+                sep(f)?;
+                f.write_str(name)?;
+            }
+        }
+
+        // Print reserved bits as a number.
+        let known = Self::NAMES.iter().fold(0, |a, &(m, _)| a | m);
+        let unknown = self.0 & !known;
+
+        if unknown != 0 || self.0 == 0 {
+            sep(f)?;
+            write!(f, "{unknown:#x}")?;
+        }
+
+        f.write_str(")")
     }
 }
 
+/// Formats the attributes with one character for each flag in the order of `RHSDA`, or `-` if
+/// the flag is not set.
+impl Display for FileAttributes {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (mask, name) in Self::NAMES {
+            if (self.0 & mask) != 0 {
+                f.write_char(name.as_bytes()[0] as char)?;
+            } else {
+                f.write_char('-')?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Specifies which data to feed in [`Root::hash_volume()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashMode {
+    /// Main Boot Region, FAT Region and the allocated clusters.
+    Volume,
+    /// The allocated clusters only.
+    AllocatedClusters,
+}
+
+/// Represents an error when [`Root::open_upcase_table()`] or [`Root::open_allocation_bitmap()`]
+/// fails.
+#[derive(Debug, Error)]
+pub enum OpenMetadataError {
+    #[error("cannot create a clusters reader for allocation {0}")]
+    CreateClustersReaderFailed(ClusterAllocation, #[source] cluster::NewError),
+}
+
+/// Represents an error when [`Root::read_entry_at()`] fails.
+#[derive(Debug, Error)]
+pub enum ReadEntryError {
+    #[error("entry #{1} on cluster #{0} is not a valid location")]
+    InvalidLocation(usize, usize),
+
+    #[error("cannot create a clusters reader")]
+    CreateClustersReaderFailed(#[source] self::cluster::NewError),
+
+    #[error("cannot read an entry")]
+    ReadEntryFailed(#[source] self::entries::ReaderError),
+
+    #[error("entry #{0} on cluster #{1} is not a file entry")]
+    NotFileEntry(usize, usize),
+
+    #[error("cannot load file entry")]
+    LoadFileEntryFailed(#[source] self::entries::FileEntryError),
+}
+
+/// Represents an error when [`Root::verify_fats()`] fails.
+#[derive(Error)]
+pub enum VerifyFatsError<P: DiskPartition> {
+    #[error("cannot load FAT #{0}")]
+    LoadFatFailed(usize, #[source] self::fat::LoadError<P>),
+
+    #[error("entry for cluster #{0} is not the same on all FATs")]
+    Mismatch(usize),
+}
+
+impl<P: DiskPartition> Debug for VerifyFatsError<P> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::LoadFatFailed(arg0, arg1) => f
+                .debug_tuple("LoadFatFailed")
+                .field(arg0)
+                .field(arg1)
+                .finish(),
+            Self::Mismatch(arg0) => f.debug_tuple("Mismatch").field(arg0).finish(),
+        }
+    }
+}
+
+/// Represents an error when [`Root::open_file()`] fails.
+#[derive(Debug, Error)]
+pub enum OpenFileError {
+    #[error("the path is not valid")]
+    InvalidPath,
+
+    #[error("no such file")]
+    NotFound,
+
+    #[error("a component of the path is not a directory")]
+    NotDirectory,
+
+    #[error("the path is a directory")]
+    IsDirectory,
+
+    #[error("cannot read a directory")]
+    ReadDirectoryFailed(#[source] DirectoryError),
+
+    #[error("cannot create a file object")]
+    CreateFileObjectFailed(#[source] file::NewError),
+}
+
+impl From<ResolveError> for OpenFileError {
+    fn from(value: ResolveError) -> Self {
+        match value {
+            ResolveError::InvalidPath => Self::InvalidPath,
+            ResolveError::NotFound => Self::NotFound,
+            ResolveError::NotDirectory => Self::NotDirectory,
+            ResolveError::ReadDirectoryFailed(e) => Self::ReadDirectoryFailed(e),
+        }
+    }
+}
+
+/// Represents an error when [`Root::lookup()`] fails.
+#[derive(Debug, Error)]
+pub enum LookupError {
+    #[error("{component} does not exist")]
+    NotFound { component: String },
+
+    #[error("{component} is not a directory")]
+    NotADirectory { component: String },
+
+    #[error("cannot read a directory")]
+    Io(#[source] DirectoryError),
+}
+
+/// Represents an error when [`Root::rename()`] fails.
+#[derive(Error)]
+pub enum RenameError<P: DiskPartition> {
+    #[error("the path is not valid")]
+    InvalidPath,
+
+    #[error("no such file or directory")]
+    NotFound,
+
+    #[error("a component of the path is not a directory")]
+    NotDirectory,
+
+    #[error("the new name is not valid")]
+    InvalidName,
+
+    #[error("the destination already exists")]
+    AlreadyExists,
+
+    #[error("cannot move a directory into itself")]
+    IntoSubtree,
+
+    #[error("cannot read a directory")]
+    ReadDirectoryFailed(#[source] DirectoryError),
+
+    #[error("cannot update a directory")]
+    UpdateDirectoryFailed(#[source] CreateError<P>),
+}
+
+impl<P: DiskPartition> Debug for RenameError<P> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidPath => write!(f, "InvalidPath"),
+            Self::NotFound => write!(f, "NotFound"),
+            Self::NotDirectory => write!(f, "NotDirectory"),
+            Self::InvalidName => write!(f, "InvalidName"),
+            Self::AlreadyExists => write!(f, "AlreadyExists"),
+            Self::IntoSubtree => write!(f, "IntoSubtree"),
+            Self::ReadDirectoryFailed(arg0) => {
+                f.debug_tuple("ReadDirectoryFailed").field(arg0).finish()
+            }
+            Self::UpdateDirectoryFailed(arg0) => {
+                f.debug_tuple("UpdateDirectoryFailed").field(arg0).finish()
+            }
+        }
+    }
+}
+
+/// Represents an error for [`Root::set_volume_label()`].
+#[derive(Error)]
+pub enum SetVolumeLabelError<P: DiskPartition> {
+    #[error("the label has more than 11 characters")]
+    TooLong,
+
+    #[error("cannot read the root directory")]
+    ReadDirectoryFailed(#[source] DirectoryError),
+
+    #[error("cannot read an entry in the root directory")]
+    ReadEntryFailed(#[source] crate::entries::ReaderError),
+
+    #[error("cannot update the root directory")]
+    UpdateDirectoryFailed(#[source] CreateError<P>),
+}
+
+impl<P: DiskPartition> Debug for SetVolumeLabelError<P> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::TooLong => write!(f, "TooLong"),
+            Self::ReadDirectoryFailed(arg0) => {
+                f.debug_tuple("ReadDirectoryFailed").field(arg0).finish()
+            }
+            Self::ReadEntryFailed(arg0) => f.debug_tuple("ReadEntryFailed").field(arg0).finish(),
+            Self::UpdateDirectoryFailed(arg0) => {
+                f.debug_tuple("UpdateDirectoryFailed").field(arg0).finish()
+            }
+        }
+    }
+}
+
+impl<P: DiskPartition> From<ResolveError> for RenameError<P> {
+    fn from(value: ResolveError) -> Self {
+        match value {
+            ResolveError::InvalidPath => Self::InvalidPath,
+            ResolveError::NotFound => Self::NotFound,
+            ResolveError::NotDirectory => Self::NotDirectory,
+            ResolveError::ReadDirectoryFailed(e) => Self::ReadDirectoryFailed(e),
+        }
+    }
+}
+
+/// Represents an error when resolving a path fails.
+enum ResolveError {
+    InvalidPath,
+    NotFound,
+    NotDirectory,
+    ReadDirectoryFailed(DirectoryError),
+}
+
 /// Represents an error when [`Root::open()`] fails.
 #[derive(Error)]
 pub enum RootError<P: DiskPartition> {
-    #[error("cannot read main boot region")]
-    ReadMainBootFailed(#[source] P::Err),
+    #[error("cannot read main boot region at {0:#x}")]
+    ReadMainBootFailed(u64, #[source] P::Err),
 
     #[error("image is not exFAT")]
     NotExFat,
 
+    #[error("Boot Checksum mismatched")]
+    InvalidBootChecksum,
+
     #[error("invalid BytesPerSectorShift")]
     InvalidBytesPerSectorShift,
 
@@ -315,6 +1442,15 @@ pub enum RootError<P: DiskPartition> {
     #[error("invalid NumberOfFats")]
     InvalidNumberOfFats,
 
+    #[error("ActiveFat is 1 but the volume has only one FAT")]
+    InvalidActiveFat,
+
+    #[error("invalid volume geometry")]
+    InvalidParams(#[source] self::param::ParamsError),
+
+    #[error("the partition is smaller than {0} bytes required by the volume")]
+    ImageTooSmall(u64),
+
     #[error("cannot read FAT region")]
     ReadFatRegionFailed(#[source] self::fat::LoadError<P>),
 
@@ -359,18 +1495,36 @@ pub enum RootError<P: DiskPartition> {
 
     #[error("no Up-case Table available")]
     NoUpcaseTable,
+
+    #[error("cannot load Allocation Bitmap")]
+    LoadAllocationBitmapFailed(#[source] self::bitmap::LoadError),
+
+    #[error("cannot load Up-case Table")]
+    LoadUpcaseTableFailed(#[source] self::upcase::LoadError),
+
+    #[error("cluster #{0} is used by more than one file or directory")]
+    CrossLinkedCluster(usize),
+
+    #[error("cannot read a directory")]
+    ReadDirectoryFailed(#[source] DirectoryError),
 }
 
 impl<P: DiskPartition> Debug for RootError<P> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
-            Self::ReadMainBootFailed(arg0) => {
-                f.debug_tuple("ReadMainBootFailed").field(arg0).finish()
-            }
+            Self::ReadMainBootFailed(arg0, arg1) => f
+                .debug_tuple("ReadMainBootFailed")
+                .field(arg0)
+                .field(arg1)
+                .finish(),
             Self::NotExFat => write!(f, "NotExFat"),
+            Self::InvalidBootChecksum => write!(f, "InvalidBootChecksum"),
             Self::InvalidBytesPerSectorShift => write!(f, "InvalidBytesPerSectorShift"),
             Self::InvalidSectorsPerClusterShift => write!(f, "InvalidSectorsPerClusterShift"),
             Self::InvalidNumberOfFats => write!(f, "InvalidNumberOfFats"),
+            Self::InvalidActiveFat => write!(f, "InvalidActiveFat"),
+            Self::InvalidParams(arg0) => f.debug_tuple("InvalidParams").field(arg0).finish(),
+            Self::ImageTooSmall(arg0) => f.debug_tuple("ImageTooSmall").field(arg0).finish(),
             Self::ReadFatRegionFailed(arg0) => {
                 f.debug_tuple("ReadFatRegionFailed").field(arg0).finish()
             }
@@ -411,6 +1565,19 @@ impl<P: DiskPartition> Debug for RootError<P> {
                 .finish(),
             Self::NoAllocationBitmap => write!(f, "NoAllocationBitmap"),
             Self::NoUpcaseTable => write!(f, "NoUpcaseTable"),
+            Self::LoadAllocationBitmapFailed(arg0) => f
+                .debug_tuple("LoadAllocationBitmapFailed")
+                .field(arg0)
+                .finish(),
+            Self::LoadUpcaseTableFailed(arg0) => {
+                f.debug_tuple("LoadUpcaseTableFailed").field(arg0).finish()
+            }
+            Self::CrossLinkedCluster(arg0) => {
+                f.debug_tuple("CrossLinkedCluster").field(arg0).finish()
+            }
+            Self::ReadDirectoryFailed(arg0) => {
+                f.debug_tuple("ReadDirectoryFailed").field(arg0).finish()
+            }
         }
     }
 }
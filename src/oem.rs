@@ -0,0 +1,72 @@
+use byteorder::{ByteOrder, LE};
+
+/// GUID of the Flash Parameters ({0A0C7E46-3399-4021-90C8-FA6D389C4BA2}) as stored on the disk.
+const FLASH_PARAMETERS: [u8; 16] = [
+    0x46, 0x7e, 0x0c, 0x0a, 0x99, 0x33, 0x21, 0x40, 0x90, 0xc8, 0xfa, 0x6d, 0x38, 0x9c, 0x4b, 0xa2,
+];
+
+/// Represents the Flash Parameters in the OEM Parameters sector.
+#[derive(Debug, Clone, Copy)]
+pub struct OemParameters {
+    erase_block_size: u32,
+    page_size: u32,
+    spare_sectors: u32,
+    random_access_time: u32,
+    programming_time: u32,
+    read_cycle: u32,
+    write_cycle: u32,
+}
+
+impl OemParameters {
+    /// Finds the Flash Parameters in `sector`, which is the OEM Parameters sector.
+    pub(crate) fn parse(sector: &[u8]) -> Option<Self> {
+        let data = sector
+            .chunks_exact(48)
+            .take(10)
+            .find(|p| p[..16] == FLASH_PARAMETERS)?;
+
+        Some(Self {
+            erase_block_size: LE::read_u32(&data[16..]),
+            page_size: LE::read_u32(&data[20..]),
+            spare_sectors: LE::read_u32(&data[24..]),
+            random_access_time: LE::read_u32(&data[28..]),
+            programming_time: LE::read_u32(&data[32..]),
+            read_cycle: LE::read_u32(&data[36..]),
+            write_cycle: LE::read_u32(&data[40..]),
+        })
+    }
+
+    /// Returns the size of the erase block in bytes.
+    pub fn erase_block_size(&self) -> u32 {
+        self.erase_block_size
+    }
+
+    /// Returns the size of the page in bytes.
+    pub fn page_size(&self) -> u32 {
+        self.page_size
+    }
+
+    pub fn spare_sectors(&self) -> u32 {
+        self.spare_sectors
+    }
+
+    /// Returns the random access time in nanoseconds.
+    pub fn random_access_time(&self) -> u32 {
+        self.random_access_time
+    }
+
+    /// Returns the programming time in nanoseconds.
+    pub fn programming_time(&self) -> u32 {
+        self.programming_time
+    }
+
+    /// Returns the read cycle time in nanoseconds.
+    pub fn read_cycle(&self) -> u32 {
+        self.read_cycle
+    }
+
+    /// Returns the write cycle time in nanoseconds.
+    pub fn write_cycle(&self) -> u32 {
+        self.write_cycle
+    }
+}
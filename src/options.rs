@@ -0,0 +1,135 @@
+use alloc::sync::Arc;
+use core::fmt::{Debug, Formatter};
+use spin::Mutex;
+
+/// Options for [`Root::open_with()`][crate::Root::open_with()].
+#[derive(Clone, Default)]
+pub struct OpenOptions {
+    pub(crate) cache_size: usize,
+    pub(crate) ignore_unknown_entries: bool,
+    pub(crate) skip_checksums: bool,
+    pub(crate) skip_deleted_entries: bool,
+    pub(crate) strict_names: bool,
+    pub(crate) lenient_volume_label: bool,
+    pub(crate) check_cross_links: bool,
+    pub(crate) on_warning: Option<Arc<WarningHandler>>,
+}
+
+impl OpenOptions {
+    /// Creates a new [`OpenOptions`] with all options disabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of clusters to keep in memory after they were read. Zero disables the
+    /// cache, which is the default.
+    pub fn cache_size(mut self, v: usize) -> Self {
+        self.cache_size = v;
+        self
+    }
+
+    /// Skips unknown critical directory entries instead of failing with
+    /// [`RootError::UnknownEntry`] or [`DirectoryError::NotFileEntry`]. Unknown benign entries are
+    /// always skipped.
+    ///
+    /// [`RootError::UnknownEntry`]: crate::RootError::UnknownEntry
+    /// [`DirectoryError::NotFileEntry`]: crate::DirectoryError::NotFileEntry
+    pub fn ignore_unknown_entries(mut self, v: bool) -> Self {
+        self.ignore_unknown_entries = v;
+        self
+    }
+
+    /// Skips verification of Boot Checksum, TableChecksum, SetChecksum and NameHash.
+    pub fn skip_checksums(mut self, v: bool) -> Self {
+        self.skip_checksums = v;
+        self
+    }
+
+    /// Continues reading a directory past the deleted entries until the end of directory entry.
+    ///
+    /// By default a directory stops at the first entry that is not in use, which hides the files
+    /// after a deleted file if the directory was written by an implementation that does not move
+    /// the end of directory.
+    pub fn skip_deleted_entries(mut self, v: bool) -> Self {
+        self.skip_deleted_entries = v;
+        self
+    }
+
+    /// Fails with [`FileEntryError::InvalidName`] if a file name is not a valid UTF-16 instead of
+    /// replacing the unpaired surrogates with `U+FFFD`.
+    ///
+    /// [`FileEntryError::InvalidName`]: crate::entries::FileEntryError::InvalidName
+    pub fn strict_names(mut self, v: bool) -> Self {
+        self.strict_names = v;
+        self
+    }
+
+    /// Accepts a volume label with more than 11 characters or a malformed UTF-16. The label will be
+    /// truncated to what the entry can hold and unpaired surrogates will be replaced with
+    /// `U+FFFD`.
+    pub fn lenient_volume_label(mut self, v: bool) -> Self {
+        self.lenient_volume_label = v;
+        self
+    }
+
+    /// Checks if any cluster is used by more than one file or directory and fails with
+    /// [`RootError::CrossLinkedCluster`] if so.
+    ///
+    /// This reads every directory on the volume so it can be slow on a large volume.
+    ///
+    /// [`RootError::CrossLinkedCluster`]: crate::RootError::CrossLinkedCluster
+    pub fn check_cross_links(mut self, v: bool) -> Self {
+        self.check_cross_links = v;
+        self
+    }
+
+    /// Sets a function to be called when [`Root::open_with()`][crate::Root::open_with()] found a
+    /// problem that does not prevent the volume from being opened.
+    pub fn on_warning(mut self, f: impl FnMut(OpenWarning) + Send + 'static) -> Self {
+        self.on_warning = Some(Arc::new(Mutex::new(f)));
+        self
+    }
+
+    pub(crate) fn warn(&self, w: OpenWarning) {
+        if let Some(f) = &self.on_warning {
+            (f.lock())(w);
+        }
+    }
+}
+
+impl Debug for OpenOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OpenOptions")
+            .field("cache_size", &self.cache_size)
+            .field("ignore_unknown_entries", &self.ignore_unknown_entries)
+            .field("skip_checksums", &self.skip_checksums)
+            .field("skip_deleted_entries", &self.skip_deleted_entries)
+            .field("strict_names", &self.strict_names)
+            .field("lenient_volume_label", &self.lenient_volume_label)
+            .field("check_cross_links", &self.check_cross_links)
+            .field("on_warning", &self.on_warning.is_some())
+            .finish()
+    }
+}
+
+type WarningHandler = Mutex<dyn FnMut(OpenWarning) + Send>;
+
+/// Represents a problem that [`Root::open_with()`][crate::Root::open_with()] was able to recover
+/// from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenWarning {
+    /// The main boot region is corrupted so the backup one was used.
+    UsedBackupBoot,
+
+    /// VolumeDirty is set, which means the volume was not cleanly unmounted.
+    VolumeDirty,
+
+    /// MediaFailure is set, which means the media has reported a failure.
+    MediaFailure,
+
+    /// An unknown entry at the specified index on the specified cluster was skipped.
+    UnknownEntrySkipped(usize, usize),
+
+    /// The volume label is malformed and was fixed.
+    InvalidVolumeLabel,
+}
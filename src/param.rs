@@ -1,3 +1,5 @@
+use thiserror::Error;
+
 pub(crate) struct Params {
     pub fat_offset: u64,          // in sector
     pub fat_length: u64,          // in sector
@@ -33,6 +35,73 @@ impl Params {
     pub fn cluster_size(&self) -> u64 {
         self.bytes_per_sector * self.sectors_per_cluster
     }
+
+    /// Gets the layout of the regions in bytes.
+    pub fn geometry(&self) -> Geometry {
+        let cluster_size = self.cluster_size();
+
+        Geometry {
+            fat_offset_bytes: self.fat_offset * self.bytes_per_sector,
+            fat_length_bytes: self.fat_length * self.bytes_per_sector,
+            cluster_heap_offset_bytes: self.cluster_heap_offset * self.bytes_per_sector,
+            cluster_size,
+            cluster_count: self.cluster_count,
+            total_data_bytes: self.cluster_count as u64 * cluster_size,
+        }
+    }
+
+    /// Checks if the regions and the root directory are at the valid location.
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        // This also make sure the cluster index will never overflow on a 32-bit target.
+        if self.cluster_count > 0xFFFFFFF5 {
+            return Err(ParamsError::InvalidClusterCount);
+        }
+
+        // The FAT region must end before the cluster heap.
+        let fat_end = self.fat_length * u64::from(self.number_of_fats) + self.fat_offset;
+
+        if self.cluster_heap_offset < fat_end {
+            return Err(ParamsError::OverlappingRegions);
+        }
+
+        // Check if the root directory is in the cluster heap.
+        let root = self.first_cluster_of_root_directory;
+
+        if root < 2 || root - 2 >= self.cluster_count {
+            return Err(ParamsError::RootDirClusterOutOfRange(root));
+        }
+
+        Ok(())
+    }
+}
+
+/// The layout of a volume. All offsets are relative to the beginning of the partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Geometry {
+    /// Offset of the first FAT (FatOffset).
+    pub fat_offset_bytes: u64,
+    /// Size of each FAT (FatLength). The second FAT, if any, immediately follows the first one.
+    pub fat_length_bytes: u64,
+    /// Offset of the Cluster Heap (ClusterHeapOffset), which is the offset of cluster #2.
+    pub cluster_heap_offset_bytes: u64,
+    pub cluster_size: u64,
+    /// Number of clusters in the Cluster Heap (ClusterCount).
+    pub cluster_count: usize,
+    /// Size of the Cluster Heap.
+    pub total_data_bytes: u64,
+}
+
+/// Represents an error for [`Params::validate()`].
+#[derive(Debug, Error)]
+pub enum ParamsError {
+    #[error("invalid ClusterCount")]
+    InvalidClusterCount,
+
+    #[error("FAT region overlaps with the cluster heap")]
+    OverlappingRegions,
+
+    #[error("FirstClusterOfRootDirectory #{0} is not in the cluster heap")]
+    RootDirClusterOutOfRange(usize),
 }
 
 #[derive(Clone, Copy)]
@@ -43,6 +112,14 @@ impl VolumeFlags {
     pub fn active_fat(self) -> usize {
         (self.0 & 1) as usize
     }
+
+    pub fn volume_dirty(self) -> bool {
+        (self.0 & 2) != 0
+    }
+
+    pub fn media_failure(self) -> bool {
+        (self.0 & 4) != 0
+    }
 }
 
 impl From<u16> for VolumeFlags {
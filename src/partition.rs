@@ -0,0 +1,186 @@
+use crate::disk::DiskPartition;
+use alloc::vec;
+use alloc::vec::Vec;
+use byteorder::{ByteOrder, LE};
+use core::fmt::Debug;
+use thiserror::Error;
+
+/// Type GUID of Microsoft Basic Data partition in the on-disk format.
+const BASIC_DATA_GUID: [u8; 16] = [
+    0xa2, 0xa0, 0xd0, 0xeb, 0xe5, 0xb9, 0x33, 0x44, 0x87, 0xc0, 0x68, 0xb6, 0xb7, 0x26, 0x99, 0xc7,
+];
+
+/// Maximum size of the partition entries we accept, in bytes.
+const MAX_GPT_ENTRIES_SIZE: usize = 1024 * 1024;
+
+/// Locates exFAT partitions on `disk` by reading its MBR or GPT.
+///
+/// A partition is considered exFAT if its type indicates Microsoft Basic Data (or 0x07 for MBR)
+/// and its boot sector has exFAT signature. LBA on MBR is assumed to be 512 bytes. Returns an
+/// empty list if `disk` does not have a partition table.
+pub fn find_partitions<P: DiskPartition>(
+    disk: &P,
+) -> Result<Vec<PartitionEntry>, FindPartitionsError<P>> {
+    // Read MBR.
+    let mut mbr = [0u8; 512];
+
+    if let Err(e) = disk.read_exact(0, &mut mbr) {
+        return Err(FindPartitionsError::ReadFailed(0, e));
+    }
+
+    // An exFAT volume without a partition table has the same signature as MBR.
+    if mbr[510..] != [0x55, 0xaa] || &mbr[3..11] == b"EXFAT   " {
+        return Ok(Vec::new());
+    }
+
+    // Check if protective MBR.
+    let records: Vec<&[u8]> = mbr[446..510].chunks(16).collect();
+    let candidates = if records.iter().any(|r| r[4] == 0xee) {
+        read_gpt(disk)?
+    } else {
+        let mut candidates = Vec::new();
+
+        for r in records {
+            let start = LE::read_u32(&r[8..]) as u64;
+            let sectors = LE::read_u32(&r[12..]) as u64;
+
+            if r[4] == 0x07 && start != 0 && sectors != 0 {
+                candidates.push(PartitionEntry {
+                    start_offset: start * 512,
+                    size: sectors * 512,
+                });
+            }
+        }
+
+        candidates
+    };
+
+    // Check file system name in the boot sector.
+    let mut partitions = Vec::with_capacity(candidates.len());
+
+    for p in candidates {
+        let mut name = [0u8; 11];
+
+        if let Err(e) = disk.read_exact(p.start_offset, &mut name) {
+            return Err(FindPartitionsError::ReadFailed(p.start_offset, e));
+        }
+
+        if &name[3..] == b"EXFAT   " {
+            partitions.push(p);
+        }
+    }
+
+    Ok(partitions)
+}
+
+fn read_gpt<P: DiskPartition>(disk: &P) -> Result<Vec<PartitionEntry>, FindPartitionsError<P>> {
+    // Find GPT header. The header is on the LBA 1 so we need to try all possible sector size. The
+    // disk may be too small for the larger one.
+    let mut header = [0u8; 92];
+    let mut sector_size = None;
+
+    for size in [512u64, 4096] {
+        if disk.read_exact(size, &mut header).is_ok() && &header[..8] == b"EFI PART" {
+            sector_size = Some(size);
+            break;
+        }
+    }
+
+    let sector_size = match sector_size {
+        Some(v) => v,
+        None => return Err(FindPartitionsError::InvalidGptHeader),
+    };
+
+    // Load fields.
+    let entries_lba = LE::read_u64(&header[72..]);
+    let entry_count = LE::read_u32(&header[80..]) as usize;
+    let entry_size = LE::read_u32(&header[84..]) as usize;
+
+    // SizeOfPartitionEntry must be 128 multiplied by a power of two.
+    if entry_size < 128 || !entry_size.is_power_of_two() || entry_count > 1024 {
+        return Err(FindPartitionsError::InvalidGptHeader);
+    }
+
+    let entries_size = match entry_count.checked_mul(entry_size) {
+        Some(v) if v <= MAX_GPT_ENTRIES_SIZE => v,
+        _ => return Err(FindPartitionsError::InvalidGptHeader),
+    };
+
+    // Read partition entries.
+    let offset = match entries_lba.checked_mul(sector_size) {
+        Some(v) => v,
+        None => return Err(FindPartitionsError::InvalidGptHeader),
+    };
+
+    let mut entries = vec![0u8; entries_size];
+
+    if let Err(e) = disk.read_exact(offset, &mut entries) {
+        return Err(FindPartitionsError::ReadFailed(offset, e));
+    }
+
+    // Parse partition entries.
+    let mut partitions = Vec::new();
+
+    for e in entries.chunks(entry_size) {
+        let first = LE::read_u64(&e[32..]);
+        let last = LE::read_u64(&e[40..]);
+
+        if e[..16] != BASIC_DATA_GUID || last < first {
+            continue;
+        }
+
+        let (start_offset, size) = match (
+            first.checked_mul(sector_size),
+            (last - first + 1).checked_mul(sector_size),
+        ) {
+            (Some(o), Some(s)) => (o, s),
+            _ => return Err(FindPartitionsError::InvalidGptHeader),
+        };
+
+        partitions.push(PartitionEntry { start_offset, size });
+    }
+
+    Ok(partitions)
+}
+
+/// Represents an exFAT partition that was found by [`find_partitions()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionEntry {
+    start_offset: u64,
+    size: u64,
+}
+
+impl PartitionEntry {
+    /// Gets the offset of the partition on the disk, in bytes.
+    ///
+    /// This value can be passed to [`Image::open_at()`][crate::image::Image::open_at()].
+    pub fn start_offset(&self) -> u64 {
+        self.start_offset
+    }
+
+    /// Gets the size of the partition, in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// Represents an error when [`find_partitions()`] fails.
+#[derive(Error)]
+pub enum FindPartitionsError<P: DiskPartition> {
+    #[error("cannot read the data at {0:#x}")]
+    ReadFailed(u64, #[source] P::Err),
+
+    #[error("invalid GPT header")]
+    InvalidGptHeader,
+}
+
+impl<P: DiskPartition> Debug for FindPartitionsError<P> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ReadFailed(arg0, arg1) => {
+                f.debug_tuple("ReadFailed").field(arg0).field(arg1).finish()
+            }
+            Self::InvalidGptHeader => write!(f, "InvalidGptHeader"),
+        }
+    }
+}
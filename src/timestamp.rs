@@ -1,3 +1,7 @@
+use core::cmp::Ordering;
+use core::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy)]
 pub struct Timestamps {
     created: Timestamp,
     modified: Timestamp,
@@ -26,11 +30,64 @@ impl Timestamps {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
 pub struct Timestamp {
     timestamp: u32,
     ms_increment: u8,
     // Offset from UTC in 15 minute intervals
     utc_offset: i8,
+    utc_offset_valid: bool,
+}
+
+/// Timestamps are compared by the instant they represent so two timestamps with the same local
+/// time but different UTC offsets are not equal.
+impl PartialEq for Timestamp {
+    fn eq(&self, other: &Self) -> bool {
+        self.unix_millis() == other.unix_millis()
+    }
+}
+
+impl Eq for Timestamp {}
+
+impl PartialOrd for Timestamp {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timestamp {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.unix_millis().cmp(&other.unix_millis())
+    }
+}
+
+/// Formats the timestamp in ISO 8601 (e.g. `2023-03-06T13:03:18+00:00`). The milliseconds will be
+/// included if it is not zero.
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let date = self.date();
+        let time = self.time();
+
+        // The 10ms increment can be up to 1990 milliseconds.
+        let second = (self.timestamp & 0x1F) * 2 + u32::from(self.ms_increment / 100);
+        let ms = u16::from(self.ms_increment % 100) * 10;
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            date.year, date.month, date.day, time.hour, time.minute, second
+        )?;
+
+        if ms != 0 {
+            write!(f, ".{ms:03}")?;
+        }
+
+        let offset = i16::from(self.utc_offset) * 15;
+        let sign = if offset < 0 { '-' } else { '+' };
+        let offset = offset.unsigned_abs();
+
+        write!(f, "{}{:02}:{:02}", sign, offset / 60, offset % 60)
+    }
 }
 
 pub struct Date {
@@ -52,9 +109,122 @@ impl Timestamp {
             timestamp,
             ms_increment,
             utc_offset,
+            utc_offset_valid: true,
+        }
+    }
+
+    /// Creates a [`Timestamp`] from the fields of File entry. The offset will be zero if OffsetValid
+    /// is not set.
+    pub(crate) fn from_fields(timestamp: u32, ms_increment: u8, utc_offset: u8) -> Self {
+        let valid = (utc_offset & 0x80) != 0;
+
+        Timestamp {
+            timestamp,
+            ms_increment,
+            // The offset is a signed 7-bit value.
+            utc_offset: if valid {
+                ((utc_offset << 1) as i8) >> 1
+            } else {
+                0
+            },
+            utc_offset_valid: valid,
         }
     }
 
+    /// Creates a [`Timestamp`] for the current time in UTC.
+    #[cfg(feature = "std")]
+    pub fn now() -> Self {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let secs = now.as_secs();
+        let (days, secs) = (secs / 86400, secs % 86400);
+
+        // Convert days since Unix epoch to a civil date. See
+        // https://howardhinnant.github.io/date_algorithms.html#civil_from_days for the algorithm.
+        let z = days + 719468;
+        let era = z / 146097;
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + u64::from(month <= 2);
+
+        // The year is limited to 1980 - 2107.
+        let year = year.clamp(1980, 2107) - 1980;
+        let timestamp = (year << 25)
+            | (month << 21)
+            | (day << 16)
+            | ((secs / 3600) << 11)
+            | ((secs % 3600 / 60) << 5)
+            | (secs % 60 / 2);
+        let ms_increment = (secs % 2) * 100 + u64::from(now.subsec_millis() / 10);
+
+        Self::new(timestamp as u32, ms_increment as u8, 0)
+    }
+
+    /// Converts this timestamp to a [`std::time::SystemTime`]. Returns [`None`] if the date or
+    /// the time is not valid.
+    #[cfg(feature = "std")]
+    pub fn to_system_time(&self) -> Option<std::time::SystemTime> {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let date = self.date();
+        let time = self.time();
+
+        if !(1..=12).contains(&date.month)
+            || date.day == 0
+            || time.hour > 23
+            || time.minute > 59
+            || time.second > 59
+            || self.ms_increment > 199
+        {
+            return None;
+        }
+
+        let ms = u64::try_from(self.unix_millis()).ok()?;
+
+        UNIX_EPOCH.checked_add(Duration::from_millis(ms))
+    }
+
+    /// Returns the number of milliseconds since Unix epoch in UTC. The fields are not validated.
+    fn unix_millis(&self) -> i64 {
+        let date = self.date();
+
+        // Convert a civil date to days since Unix epoch. See
+        // https://howardhinnant.github.io/date_algorithms.html#days_from_civil for the algorithm.
+        let (month, day) = (i64::from(date.month), i64::from(date.day));
+        let year = i64::from(date.year) - i64::from(month <= 2);
+        let era = year / 400;
+        let yoe = year - era * 400;
+        let mp = if month > 2 { month - 3 } else { month + 9 };
+        let doy = (153 * mp + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146097 + doe - 719468;
+
+        // The timestamp is in local time so we need to subtract the offset to get UTC.
+        let secs = days * 86400
+            + i64::from((self.timestamp >> 11) & 0x1F) * 3600
+            + i64::from((self.timestamp >> 5) & 0x3F) * 60
+            + i64::from(self.timestamp & 0x1F) * 2
+            - i64::from(self.utc_offset) * 900;
+
+        secs * 1000 + i64::from(self.ms_increment) * 10
+    }
+
+    /// Returns [`Timestamp::now()`] or 1980-01-01 00:00:00 if the `std` feature is disabled.
+    pub(crate) fn now_or_min() -> Self {
+        #[cfg(feature = "std")]
+        return Self::now();
+
+        #[cfg(not(feature = "std"))]
+        return Self::new((1 << 21) | (1 << 16), 0, 0);
+    }
+
     pub fn date(&self) -> Date {
         Date {
             day: ((self.timestamp >> 16) & 0x1F) as u8,
@@ -74,4 +244,27 @@ impl Timestamp {
     pub fn utc_offset(&self) -> i8 {
         self.utc_offset
     }
+
+    /// Returns `false` if OffsetValid was not set on the disk, in which case
+    /// [`Timestamp::utc_offset()`] is zero.
+    pub fn is_utc_offset_valid(&self) -> bool {
+        self.utc_offset_valid
+    }
+
+    pub(crate) fn timestamp(&self) -> u32 {
+        self.timestamp
+    }
+
+    pub(crate) fn ms_increment(&self) -> u8 {
+        self.ms_increment
+    }
+
+    /// Returns the value for UtcOffset field of File entry.
+    pub(crate) fn utc_offset_field(&self) -> u8 {
+        if self.utc_offset_valid {
+            0x80 | (self.utc_offset as u8 & 0x7f)
+        } else {
+            0
+        }
+    }
 }
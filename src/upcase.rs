@@ -0,0 +1,127 @@
+use crate::cluster::ClustersReader;
+use crate::disk::DiskPartition;
+use crate::entries::ClusterAllocation;
+use crate::fat::Fat;
+use crate::param::Params;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use byteorder::{ByteOrder, LE};
+use thiserror::Error;
+
+/// Represents an Up-case Table.
+pub(crate) struct UpcaseTable {
+    table: Vec<u16>,
+}
+
+impl UpcaseTable {
+    pub fn load<D: DiskPartition>(
+        disk: &D,
+        params: &Arc<Params>,
+        fat: &Fat,
+        alloc: &ClusterAllocation,
+        checksum: Option<u32>,
+    ) -> Result<Self, LoadError> {
+        // The maximum size is when the table is not compressed.
+        let len = alloc.data_length();
+
+        if len == 0 || len > 0x20000 || len % 2 != 0 {
+            return Err(LoadError::InvalidDataLength);
+        }
+
+        // Read the table.
+        let mut reader =
+            match ClustersReader::new(disk, params, fat, alloc.first_cluster(), Some(len), None) {
+                Ok(v) => v,
+                Err(e) => return Err(LoadError::CreateClustersReaderFailed(e)),
+            };
+
+        let mut data = vec![0u8; len as usize];
+
+        if let Err(e) = reader.read_exact(&mut data) {
+            return Err(LoadError::ReadFailed(e));
+        }
+
+        // Verify TableChecksum.
+        if let Some(checksum) = checksum {
+            let actual = data
+                .iter()
+                .fold(0u32, |c, &b| c.rotate_right(1).wrapping_add(b.into()));
+
+            if actual != checksum {
+                return Err(LoadError::ChecksumMismatch);
+            }
+        }
+
+        // Decompress the table. A compressed range is 0xFFFF followed by the number of characters
+        // that maps to itself.
+        let mut raw = vec![0u16; data.len() / 2];
+
+        LE::read_u16_into(&data, &mut raw);
+
+        let mut table: Vec<u16> = Vec::with_capacity(0x10000);
+        let mut iter = raw.iter();
+
+        while let Some(&v) = iter.next() {
+            if v == 0xffff && table.len() != 0xffff {
+                let count = match iter.next() {
+                    Some(&v) => v as usize,
+                    None => return Err(LoadError::InvalidTable),
+                };
+
+                if table.len() + count > 0x10000 {
+                    return Err(LoadError::InvalidTable);
+                }
+
+                for _ in 0..count {
+                    table.push(table.len() as u16);
+                }
+            } else if table.len() < 0x10000 {
+                table.push(v);
+            } else {
+                return Err(LoadError::InvalidTable);
+            }
+        }
+
+        Ok(Self { table })
+    }
+
+    /// Gets the up-cased value of `c`.
+    pub fn map(&self, c: u16) -> u16 {
+        self.table.get(c as usize).copied().unwrap_or(c)
+    }
+
+    /// Up-cases `name` with this table.
+    pub fn to_uppercase(&self, name: &str) -> String {
+        let name: Vec<u16> = name.encode_utf16().map(|c| self.map(c)).collect();
+
+        String::from_utf16_lossy(&name)
+    }
+
+    /// Returns `true` if `a` and `b` are the same name according to this table.
+    pub fn eq_ignore_case(&self, a: &str, b: &str) -> bool {
+        a.encode_utf16()
+            .map(|c| self.map(c))
+            .eq(b.encode_utf16().map(|c| self.map(c)))
+    }
+}
+
+/// Represents an error for [`UpcaseTable::load()`].
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error("invalid DataLength")]
+    InvalidDataLength,
+
+    #[error("cannot create a clusters reader")]
+    CreateClustersReaderFailed(#[source] crate::cluster::NewError),
+
+    #[error("cannot read the table")]
+    ReadFailed(#[source] crate::io::ReadError),
+
+    #[error("TableChecksum mismatched")]
+    ChecksumMismatch,
+
+    #[error("the table is not valid")]
+    InvalidTable,
+}
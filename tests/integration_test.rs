@@ -1,9 +1,24 @@
-use exfat::timestamp::Timestamp;
-use exfat::{Item, Root};
-use std::fs::File;
-use std::io::Read;
-use std::path::PathBuf;
+use exfat::entries::{FileEntryError, RawEntry};
+use exfat::image::{AlignedImage, Image, MemoryImage, SeekImage};
+use exfat::io::TypedReadError;
+use exfat::param::ParamsError;
+use exfat::timestamp::{Timestamp, Timestamps};
+use exfat::{
+    find_partitions, CreateError, Directory, DirectoryError, DiskPartition, ErasedDiskPartition,
+    ErasedError, ExtractError, FileAttributes, FindPartitionsError, FormatOptions, HashMode, Item,
+    LookupError, OpenFileError, OpenWarning, ReadEntryError, RenameError, Root, RootError,
+    SetVolumeLabelError, VerifyFatsError, WritableDiskPartition,
+};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::hash::Hasher;
+use std::io::{Cursor, Error, ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
+#[allow(clippy::too_many_arguments)]
 fn check_timestamp(
     ts: &Timestamp,
     day: u8,
@@ -34,27 +49,170 @@ fn read_image() {
 
     // Check image properties.
     assert_eq!(Some("Test image"), root.volume_label());
+    assert_eq!(0, root.active_fat());
+    assert_eq!(1, root.number_of_fats());
+    assert_eq!(Some(0), root.percent_in_use());
+
+    let geometry = root.geometry();
+
+    assert_eq!(32 * 512, geometry.fat_offset_bytes);
+    assert_eq!(8 * 512, geometry.fat_length_bytes);
+    assert_eq!(48 * 512, geometry.cluster_heap_offset_bytes);
+    assert_eq!(4096, geometry.cluster_size);
+    assert_eq!(250, geometry.cluster_count);
+    assert_eq!(250 * 4096, geometry.total_data_bytes);
+
+    let boot = root.boot_sector();
+
+    assert_eq!(*b"EXFAT   ", boot.file_system_name());
+    assert_eq!(0x800, boot.volume_length());
+    assert_eq!(0x7f0ff40b, boot.volume_serial_number());
+    assert_eq!(0x100, boot.file_system_revision());
+    assert_eq!(
+        (9, 3),
+        (
+            boot.bytes_per_sector_shift(),
+            boot.sectors_per_cluster_shift()
+        )
+    );
+    assert_eq!(0x80, boot.drive_select());
+    assert_eq!("FILE1.TXT", root.to_uppercase("file1.txt"));
+    assert_eq!("ÄΣ", root.to_uppercase("äσ"));
+
+    let types: Vec<u8> = root
+        .raw_entries()
+        .expect("cannot read the root directory")
+        .take(4)
+        .map(|e| e.expect("cannot read an entry").data()[0])
+        .collect();
+
+    assert_eq!(vec![0x83, 0x81, 0x82, 0x85], types);
+
+    // Check metadata readers.
+    let mut bitmap = Vec::new();
+    let mut upcase = root
+        .open_upcase_table()
+        .expect("cannot open the up-case table");
+    let len = upcase.len();
+
+    assert_eq!((3, 2), (upcase.first_cluster(), upcase.cluster_count()));
+
+    let alloc = root.upcase_table_allocation();
+
+    assert_eq!((3, 0x16cc), (alloc.first_cluster(), alloc.data_length()));
+    assert_eq!("3:5836", alloc.to_string());
+    assert_eq!(2, root.allocation_bitmap_allocation().first_cluster());
+    assert_eq!(32, root.allocation_bitmap_allocation().data_length());
+
+    root.open_allocation_bitmap()
+        .expect("cannot open the allocation bitmap")
+        .read_to_end(&mut bitmap)
+        .expect("cannot read the allocation bitmap");
+
+    assert_eq!(0x7f, bitmap[0]);
+    assert_eq!(
+        len,
+        std::io::copy(&mut upcase, &mut std::io::sink()).unwrap()
+    );
 
     // Check items in the root of image.
-    let items = Vec::from_iter(root.into_iter());
+    let items = Vec::from_iter(root);
 
     assert_eq!(2, items.len());
+    assert_eq!(
+        vec![(3, 3), (6, 3)],
+        items.iter().map(Item::entry_set).collect::<Vec<_>>()
+    );
+    assert!(items[0].is_directory());
+    assert!(items[0].as_file().is_none());
+
+    let m = items[1].metadata();
+
+    assert_eq!("file1", m.name);
+    assert_eq!((13, 13), (m.len, m.allocated_len));
+    assert_eq!(
+        (7, false, true),
+        (m.first_cluster, m.is_directory, m.is_contiguous)
+    );
+    assert!(m.attributes.is_archive());
+    assert_eq!(FileAttributes::ARCHIVE, m.attributes.bits());
+    assert_eq!("FileAttributes(ARCHIVE)", format!("{:?}", m.attributes));
+    assert_eq!("----A", m.attributes.to_string());
+    assert_eq!(
+        "FileAttributes(READ_ONLY | DIRECTORY | 0x40)",
+        format!("{:?}", FileAttributes::from_bits(0x51))
+    );
+    assert_eq!(
+        "FileAttributes(0x0)",
+        format!("{:?}", FileAttributes::from_bits(0))
+    );
+    assert!(items[0].metadata().is_directory);
+
+    let stream = items[1].stream_entry();
+
+    assert_eq!(
+        (7, 13, 13),
+        (
+            stream.first_cluster(),
+            stream.data_length(),
+            stream.valid_data_length()
+        )
+    );
+    assert_eq!(5, stream.name_length());
+    assert!(stream.no_fat_chain());
+    assert_eq!(4096, items[0].stream_entry().data_length());
+    assert_eq!("dir1", items[0].as_directory().unwrap().name());
+    assert_eq!("file1", items[1].as_file().unwrap().name());
 
     for i in items {
         match i {
             Item::Directory(d) => {
                 // Check directory properties.
                 assert_eq!("dir1", d.name());
+                assert_eq!("/dir1", d.path());
+                assert_eq!((5, 3), d.entry_location());
+                assert_eq!(2, d.secondary_count());
+                assert!(d.is_no_fat_chain());
 
                 // Check timestamps
                 check_timestamp(d.timestamps().created(), 6, 3, 2023, 13, 2, 32, 0);
                 check_timestamp(d.timestamps().modified(), 6, 3, 2023, 13, 3, 18, 0);
                 check_timestamp(d.timestamps().accessed(), 6, 3, 2023, 13, 2, 32, 0);
 
+                // Check raw entries.
+                let entries: Vec<RawEntry> = d
+                    .raw_entries()
+                    .expect("cannot read dir1")
+                    .collect::<Result<_, _>>()
+                    .expect("cannot read an entry");
+
+                assert_eq!(128, entries.len());
+                assert_eq!(0x85, entries[0].data()[0]);
+                assert!(entries[3].ty().is_end_of_directory());
+                assert_eq!((6, 127), (entries[127].cluster(), entries[127].index()));
+
+                // Check raw data.
+                let mut data = Vec::new();
+
+                d.raw_reader()
+                    .expect("cannot open dir1")
+                    .unwrap()
+                    .read_to_end(&mut data)
+                    .expect("cannot read dir1");
+
+                assert_eq!(4096, data.len());
+                assert_eq!(6, d.raw_reader().unwrap().unwrap().first_cluster());
+                assert_eq!(1, d.raw_reader().unwrap().unwrap().cluster_count());
+                assert_eq!(entries[1].data(), &data[32..64]);
+
                 // Check items.
                 let mut items = d.open().expect("cannot open dir1");
 
                 assert_eq!(1, items.len());
+                assert_eq!(1, d.entry_count().unwrap());
+                assert_eq!(128 - 3, d.free_slots().unwrap());
+                assert_eq!("file2", d.find("FILE2").unwrap().unwrap().name());
+                assert!(d.find("file3").unwrap().is_none());
 
                 match items.remove(0) {
                     Item::Directory(_) => panic!("unexpected item in dir1"),
@@ -80,7 +238,12 @@ fn read_image() {
             Item::File(mut f) => {
                 // Check file properties.
                 assert_eq!("file1", f.name());
+                assert_eq!((5, 6), f.entry_location());
                 assert_eq!(13, f.len());
+                assert_eq!(13, f.allocated_len());
+                assert_eq!(4096, f.allocated_size().unwrap());
+                assert!(f.is_no_fat_chain());
+                assert_eq!(vec![(48 * 512 + 5 * 4096, 13)], f.extents().unwrap());
 
                 // Check file content.
                 let mut c = String::new();
@@ -88,6 +251,29 @@ fn read_image() {
                 f.read_to_string(&mut c).expect("cannot read file1");
 
                 assert_eq!("Test file 1.\n", c);
+                assert_eq!(b"Test file 1.\n", &f.read_to_vec().unwrap()[..]);
+
+                // Check seeking.
+                let mut c = String::new();
+
+                assert_eq!(10, f.seek(SeekFrom::End(-3)).unwrap());
+
+                let mut other = f.clone_reader();
+
+                f.read_to_string(&mut c).unwrap();
+
+                assert_eq!("1.\n", c);
+
+                // Check the cloned reader.
+                let mut c = [0u8; 4];
+
+                other.read_exact(&mut c).unwrap();
+
+                assert_eq!(b"Test", &c);
+                assert_eq!(13, f.stream_position().unwrap());
+
+                // Check checksum.
+                assert_eq!(0x2422c473, f.crc32().unwrap());
 
                 // Check timestamps
                 check_timestamp(f.timestamps().created(), 6, 3, 2023, 13, 3, 6, 0);
@@ -97,3 +283,2149 @@ fn read_image() {
         }
     }
 }
+
+#[test]
+fn open_lazy() {
+    // Open the image.
+    let image: PathBuf = ["tests", "exfat.img"].iter().collect();
+    let image = File::open(image).expect("cannot open exfat.img");
+    let root = Root::open_lazy(image).expect("cannot open the root directory");
+
+    assert_eq!(Some("Test image"), root.volume_label());
+
+    // List the root directory.
+    let dir = root.root_dir();
+    let items = dir.open().expect("cannot list the root directory");
+
+    assert_eq!("/", dir.path());
+    assert_eq!(
+        ["dir1", "file1"],
+        items.iter().map(Item::name).collect::<Vec<_>>().as_slice()
+    );
+    assert!(root.open_file("dir1/file2").is_ok());
+    assert_eq!(0, root.into_iter().count());
+}
+
+#[test]
+fn read_image_at_offset() {
+    // Put the image after 1 MiB of data.
+    let mut disk = vec![0xffu8; 0x100000];
+
+    append_image(&mut disk);
+
+    // Open root directory.
+    let image = Image::open_at(Cursor::new(disk), 0x100000);
+    let root = Root::open(image).expect("cannot open the root directory");
+
+    assert_eq!(Some("Test image"), root.volume_label());
+    assert_eq!(2, root.into_iter().count());
+}
+
+#[test]
+fn read_seek_image() {
+    let image = File::open("tests/exfat.img").expect("cannot open exfat.img");
+    let image = SeekImage::open(image).expect("cannot open exfat.img");
+    let root = Root::open(image).expect("cannot open the root directory");
+    let mut file = root.open_file("file1").expect("cannot open file1");
+    let mut data = String::new();
+
+    file.read_to_string(&mut data).expect("cannot read file1");
+
+    assert_eq!("Test file 1.\n", data);
+}
+
+#[test]
+fn read_aligned_image() {
+    // A reader that only accepts aligned reads like a block device opened with O_DIRECT.
+    struct Aligned(Cursor<Vec<u8>>, usize);
+
+    impl Read for Aligned {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.position() % 4096 != 0 || buf.len() % 4096 != 0 {
+                return Err(Error::from(std::io::ErrorKind::InvalidInput));
+            }
+
+            self.1 += 1;
+            self.0.read(buf)
+        }
+    }
+
+    impl Seek for Aligned {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.0.seek(pos)
+        }
+    }
+
+    // Open the image.
+    let data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let image = Image::open(Aligned(Cursor::new(data.clone()), 0)).unwrap();
+
+    assert!(Root::open(image).is_err());
+
+    let image = AlignedImage::open(Aligned(Cursor::new(data.clone()), 0), 4096).unwrap();
+    let root = Root::open(image).expect("cannot open the root directory");
+    let mut file = root.open_file("dir1/file2").expect("cannot open file2");
+
+    assert_eq!(b"Test file 2.\n", file.read_to_vec().unwrap().as_slice());
+
+    // An unaligned read should only read the head, the blocks in between and the tail.
+    let image = AlignedImage::open(Aligned(Cursor::new(data.clone()), 0), 4096).unwrap();
+    let mut buf = vec![0u8; 5 * 4096];
+
+    assert_eq!(image.read(100, &mut buf).unwrap(), buf.len());
+    assert_eq!(buf, data[100..(100 + buf.len())]);
+    assert_eq!(image.into_inner().1, 3);
+    assert!(AlignedImage::open(Cursor::new(Vec::new()), 1000).is_err());
+}
+
+#[test]
+fn find_mbr_partitions() {
+    // Create a disk with MBR.
+    let mut disk = vec![0u8; 0x100000];
+
+    disk[446 + 4] = 0x07;
+    disk[446 + 8..446 + 12].copy_from_slice(&2048u32.to_le_bytes());
+    disk[446 + 12..446 + 16].copy_from_slice(&2048u32.to_le_bytes());
+    disk[510] = 0x55;
+    disk[511] = 0xaa;
+
+    append_image(&mut disk);
+
+    // Find the partitions.
+    let disk = Image::open_at(Cursor::new(disk), 0);
+    let partitions = find_partitions(&disk).expect("cannot read partition table");
+
+    assert_eq!(1, partitions.len());
+    assert_eq!(0x100000, partitions[0].start_offset());
+    assert_eq!(0x100000, partitions[0].size());
+
+    // Open the partition.
+    let image = Image::open_at(disk.into_inner(), partitions[0].start_offset());
+    let root = Root::open(image).expect("cannot open the root directory");
+
+    assert_eq!(Some("Test image"), root.volume_label());
+
+    // A volume without a partition table has the same signature.
+    let image = Image::open(File::open("tests/exfat.img").unwrap()).unwrap();
+
+    assert!(find_partitions(&image).unwrap().is_empty());
+}
+
+#[test]
+fn find_gpt_partitions() {
+    // Create a disk with protective MBR.
+    let mut disk = vec![0u8; 0x100000];
+
+    disk[446 + 4] = 0xee;
+    disk[510] = 0x55;
+    disk[511] = 0xaa;
+
+    // Create GPT header.
+    disk[512..520].copy_from_slice(b"EFI PART");
+    disk[512 + 72..512 + 80].copy_from_slice(&2u64.to_le_bytes());
+    disk[512 + 80..512 + 84].copy_from_slice(&4u32.to_le_bytes());
+    disk[512 + 84..512 + 88].copy_from_slice(&128u32.to_le_bytes());
+
+    // Create the entries. The first one is not exFAT.
+    let guid = [
+        0xa2, 0xa0, 0xd0, 0xeb, 0xe5, 0xb9, 0x33, 0x44, 0x87, 0xc0, 0x68, 0xb6, 0xb7, 0x26, 0x99,
+        0xc7,
+    ];
+
+    for (i, first) in [34u64, 2048].into_iter().enumerate() {
+        let e = &mut disk[(1024 + i * 128)..];
+
+        e[..16].copy_from_slice(&guid);
+        e[32..40].copy_from_slice(&first.to_le_bytes());
+        e[40..48].copy_from_slice(&(first + 2047).to_le_bytes());
+    }
+
+    append_image(&mut disk);
+
+    // Find the partitions.
+    let image = Image::open_at(Cursor::new(disk.clone()), 0);
+    let partitions = find_partitions(&image).expect("cannot read partition table");
+
+    assert_eq!(1, partitions.len());
+    assert_eq!(0x100000, partitions[0].start_offset());
+    assert_eq!(0x100000, partitions[0].size());
+
+    // A disk that is too small for 4096 bytes sector should not fail with a read error.
+    let mut small = disk[..1024].to_vec();
+
+    small[512..520].fill(0);
+
+    assert!(matches!(
+        find_partitions(&Image::open_at(Cursor::new(small), 0)),
+        Err(FindPartitionsError::InvalidGptHeader)
+    ));
+
+    // Check invalid SizeOfPartitionEntry.
+    for size in [0xffffffffu32, 0x80000000, 200] {
+        disk[512 + 84..512 + 88].copy_from_slice(&size.to_le_bytes());
+
+        let image = Image::open_at(Cursor::new(disk.clone()), 0);
+
+        assert!(matches!(
+            find_partitions(&image),
+            Err(FindPartitionsError::InvalidGptHeader)
+        ));
+    }
+}
+
+#[test]
+fn open_file() {
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    // Open the image.
+    let image: PathBuf = ["tests", "exfat.img"].iter().collect();
+    let image = File::open(image).expect("cannot open exfat.img");
+    let root = Root::open(image).expect("cannot open the root directory");
+
+    assert_send_sync(&root);
+    assert!(matches!(
+        root.open_file("dir1"),
+        Err(OpenFileError::IsDirectory)
+    ));
+    assert!(matches!(
+        root.open_file("dir1/missing"),
+        Err(OpenFileError::NotFound)
+    ));
+    assert!(matches!(
+        root.open_file("file1/file2"),
+        Err(OpenFileError::NotDirectory)
+    ));
+
+    // Look up files and directories.
+    assert_eq!("file2", root.lookup("/dir1/file2").unwrap().name());
+    assert_eq!("dir1", root.lookup("dir1/").unwrap().name());
+    assert_eq!(
+        "/",
+        root.lookup("/").unwrap().as_directory().unwrap().path()
+    );
+    assert!(matches!(
+        root.lookup("/dir1/missing/x"),
+        Err(LookupError::NotFound { component }) if component == "missing"
+    ));
+    assert!(matches!(
+        root.lookup("/file1/x"),
+        Err(LookupError::NotADirectory { component }) if component == "file1"
+    ));
+
+    // Read the files from multiple threads.
+    std::thread::scope(|s| {
+        let threads = [
+            ("file1", "Test file 1.\n"),
+            ("/DIR1/file2", "Test file 2.\n"),
+        ]
+        .map(|(path, content)| {
+            let root = &root;
+
+            s.spawn(move || {
+                let mut f = root.open_file(path).expect("cannot open the file");
+
+                assert_send_sync(&f);
+                assert_eq!(content.as_bytes(), f.read_to_vec().unwrap().as_slice());
+
+                let mut copied = Vec::new();
+
+                assert_eq!(content.len() as u64, f.copy_to(&mut copied).unwrap());
+                assert_eq!(content.as_bytes(), copied.as_slice());
+            })
+        });
+
+        for t in threads {
+            t.join().unwrap();
+        }
+    });
+}
+
+#[cfg(feature = "unicode-normalization")]
+#[test]
+fn find_normalized() {
+    // Open the image.
+    let image: PathBuf = ["tests", "exfat.img"].iter().collect();
+    let image = File::open(image).expect("cannot open exfat.img");
+    let root = Root::open(image).expect("cannot open the root directory");
+
+    assert_eq!(root.find_normalized("FILE1").unwrap().name(), "file1");
+    assert_eq!(root.find_normalized("dir1").unwrap().name(), "dir1");
+    assert!(root.find_normalized("file1\u{301}").is_none());
+}
+
+#[cfg(feature = "glob")]
+#[test]
+fn glob() {
+    // Open the image.
+    let image: PathBuf = ["tests", "exfat.img"].iter().collect();
+    let image = File::open(image).expect("cannot open exfat.img");
+    let root = Root::open(image).expect("cannot open the root directory");
+    let glob = |pattern: &str| {
+        let mut paths: Vec<String> = root.glob(pattern).map(|r| r.unwrap().0).collect();
+
+        paths.sort();
+        paths
+    };
+
+    assert_eq!(["/dir1/file2", "/file1"], glob("**/file?").as_slice());
+    assert_eq!(["/dir1/file2"], glob("/DIR*/*").as_slice());
+    assert_eq!(["/file1"], glob("F*1").as_slice());
+    assert_eq!(["/dir1/file2"], glob("**/dir1/**/*2").as_slice());
+    assert!(glob("*").iter().all(|p| p == "/file1"));
+    assert!(glob("file").is_empty());
+    assert!(glob("dir1").is_empty());
+}
+
+#[test]
+fn extract_to() {
+    // Open the image.
+    let image: PathBuf = ["tests", "exfat.img"].iter().collect();
+    let image = File::open(image).expect("cannot open exfat.img");
+    let root = Root::open(image).expect("cannot open the root directory");
+    let dest = std::env::temp_dir().join(format!("exfat-{}-extract", std::process::id()));
+
+    // Extract the volume.
+    root.extract_to(&dest).expect("cannot extract the volume");
+
+    let file1 = std::fs::read(dest.join("file1")).unwrap();
+    let file2 = std::fs::read(dest.join("dir1").join("file2")).unwrap();
+
+    assert_eq!(b"Test file 1.\n", file1.as_slice());
+    assert_eq!(b"Test file 2.\n", file2.as_slice());
+
+    // Check the timestamps.
+    let file = root.open_file("file1").unwrap();
+    let modified = std::fs::metadata(dest.join("file1"))
+        .unwrap()
+        .modified()
+        .unwrap();
+
+    assert_eq!(
+        file.timestamps().modified().to_system_time(),
+        Some(modified)
+    );
+
+    // Extract again.
+    assert!(matches!(
+        root.extract_to(&dest),
+        Err(ExtractError::AlreadyExists(_))
+    ));
+
+    std::fs::remove_dir_all(dest).unwrap();
+}
+
+fn append_image(disk: &mut Vec<u8>) {
+    let image: PathBuf = ["tests", "exfat.img"].iter().collect();
+
+    File::open(image)
+        .expect("cannot open exfat.img")
+        .read_to_end(disk)
+        .expect("cannot read exfat.img");
+}
+
+#[test]
+fn read_boxed_partition() {
+    let image: PathBuf = ["tests", "exfat.img"].iter().collect();
+    let image = File::open(image).expect("cannot open exfat.img");
+    let image: Box<dyn DiskPartition<Err = Error>> = Box::new(image);
+    let root = Root::open(image).expect("cannot open the root directory");
+
+    assert_eq!(Some("Test image"), root.volume_label());
+}
+
+#[test]
+fn read_erased_partition() {
+    let image: PathBuf = ["tests", "exfat.img"].iter().collect();
+    let image: Box<dyn DiskPartition<Err = ErasedError>> = Box::new(ErasedDiskPartition::new(
+        File::open(image).expect("cannot open exfat.img"),
+    ));
+    let root = Root::open(image).expect("cannot open the root directory");
+    let mut file = root.open_file("dir1/file2").expect("cannot open file2");
+
+    assert_eq!(b"Test file 2.\n", file.read_to_vec().unwrap().as_slice());
+
+    // Reading past the end should fail.
+    let image = ErasedDiskPartition::new(MemoryImage::new(vec![0u8; 16]));
+    let e = image.read_exact(0, &mut [0u8; 32]).unwrap_err();
+
+    assert_eq!("unexpected end of partition", e.to_string());
+}
+
+#[test]
+fn oem_parameters() {
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let open = |data: &[u8]| {
+        let image = Image::open(Cursor::new(data.to_vec())).unwrap();
+        let options = exfat::OpenOptions::new().skip_checksums(true);
+
+        Root::open_with(image, options).expect("cannot open the root directory")
+    };
+
+    assert!(open(&data).oem_parameters().is_none());
+
+    // Put Flash Parameters on the second slot.
+    let guid = [
+        0x46, 0x7e, 0x0c, 0x0a, 0x99, 0x33, 0x21, 0x40, 0x90, 0xc8, 0xfa, 0x6d, 0x38, 0x9c, 0x4b,
+        0xa2,
+    ];
+    let offset = 9 * 512 + 48;
+
+    data[offset..(offset + 16)].copy_from_slice(&guid);
+    data[(offset + 16)..(offset + 20)].copy_from_slice(&0x400000u32.to_le_bytes());
+    data[(offset + 20)..(offset + 24)].copy_from_slice(&0x4000u32.to_le_bytes());
+
+    let params = open(&data).oem_parameters().unwrap();
+
+    assert_eq!(0x400000, params.erase_block_size());
+    assert_eq!(0x4000, params.page_size());
+}
+
+#[test]
+fn backup_boot() {
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let open = |data: &[u8]| Root::open(Image::open(Cursor::new(data.to_vec())).unwrap());
+
+    // The main boot region is valid.
+    let root = open(&data).expect("cannot open the root directory");
+
+    assert!(!root.used_backup_boot());
+
+    // Corrupt the boot code of the main boot sector.
+    data[200] ^= 0xff;
+
+    let root = open(&data).expect("cannot open the root directory");
+
+    assert!(root.used_backup_boot());
+    assert_eq!(Some("Test image"), root.volume_label());
+
+    // Corrupt the backup one too.
+    data[12 * 512 + 200] ^= 0xff;
+
+    assert!(matches!(open(&data), Err(RootError::InvalidBootChecksum)));
+}
+
+#[test]
+fn backup_boot_4k() {
+    // Format an image with 4096-byte sectors.
+    let image = Image::open(Cursor::new(vec![0u8; 8 * 1024 * 1024])).unwrap();
+    let opts = FormatOptions::new(8 * 1024 * 1024)
+        .bytes_per_sector(4096)
+        .volume_label("4K");
+
+    exfat::format(&image, opts).expect("cannot format the image");
+
+    let mut data = image.into_inner().into_inner();
+    let open = |data: &[u8]| Root::open(Image::open(Cursor::new(data.to_vec())).unwrap());
+
+    // Corrupt the excess space of the main boot sector, which is covered by the checksum.
+    data[1000] ^= 0xff;
+
+    let root = open(&data).expect("cannot open the root directory");
+
+    assert!(root.used_backup_boot());
+    assert_eq!(Some("4K"), root.volume_label());
+
+    // Corrupt the checksum sector of the backup one.
+    data[23 * 4096] ^= 0xff;
+
+    assert!(matches!(open(&data), Err(RootError::InvalidBootChecksum)));
+}
+
+#[test]
+fn texfat() {
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let heap = 48 * 512;
+    let fat = |i: usize, c: usize| 32 * 512 + i * 8 * 512 + c * 4;
+
+    // Add the second FAT and make it active.
+    data[110] = 2;
+    data[106] = 1;
+    data.copy_within(fat(0, 0)..fat(1, 0), fat(1, 0));
+
+    // Break the chain of Up-case Table in the first FAT so it cannot be used.
+    data[fat(0, 3)..fat(0, 4)].copy_from_slice(&0u32.to_le_bytes());
+
+    // Add the second Allocation Bitmap on cluster 9.
+    let bitmap = heap + 7 * 4096;
+    let entry = heap + 3 * 4096 + 9 * 32;
+
+    data.copy_within(heap..(heap + 32), bitmap);
+    data[bitmap] = 0xff;
+    data[fat(1, 9)..fat(1, 10)].copy_from_slice(&0xffffffffu32.to_le_bytes());
+    data[entry] = 0x81;
+    data[entry + 1] = 1;
+    data[(entry + 20)..(entry + 24)].copy_from_slice(&9u32.to_le_bytes());
+    data[(entry + 24)..(entry + 32)].copy_from_slice(&32u64.to_le_bytes());
+
+    update_boot_checksum(&mut data);
+
+    // Open the image.
+    let image = MemoryImage::new(data);
+    let options = exfat::OpenOptions::new().check_cross_links(true);
+    let root = Root::open_with(&image, options).expect("cannot open the root directory");
+
+    assert_eq!((1, 2), (root.active_fat(), root.number_of_fats()));
+    assert_eq!(
+        b"Test file 2.\n",
+        root.open_file("dir1/file2")
+            .unwrap()
+            .read_to_vec()
+            .unwrap()
+            .as_slice()
+    );
+
+    // New clusters should be allocated from the second Allocation Bitmap.
+    let mut dir1 = root
+        .into_iter()
+        .find_map(|i| match i {
+            Item::Directory(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+    let mut file = dir1.create_file("file3").unwrap();
+
+    file.set_len(1).unwrap();
+
+    assert_eq!(10, file.stream_entry().first_cluster());
+
+    drop(file);
+    drop(dir1);
+
+    let data = image.into_inner();
+
+    assert_eq!([0xff, 0x01], data[bitmap..(bitmap + 2)]);
+    assert_eq!([0x7f, 0x00], data[heap..(heap + 2)]);
+
+    // The second FAT is active on a volume with only one FAT.
+    let mut data = data;
+
+    data[110] = 1;
+
+    update_boot_checksum(&mut data);
+
+    assert!(matches!(
+        Root::open(MemoryImage::new(data)),
+        Err(RootError::InvalidActiveFat)
+    ));
+}
+
+#[test]
+fn verify_fats() {
+    // Add the second FAT and Allocation Bitmap to the image. We can use the space between the
+    // first FAT and the Cluster Heap for the second FAT.
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let root = 48 * 512 + 3 * 4096;
+
+    data[110] = 2;
+    data.copy_within((32 * 512)..(40 * 512), 40 * 512);
+    data.copy_within((root + 32)..(root + 64), root + 9 * 32);
+    data[root + 9 * 32 + 1] = 1;
+
+    for region in [0, 12 * 512] {
+        let checksum = data[region..(region + 11 * 512)]
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != 106 && i != 107 && i != 112)
+            .fold(0u32, |c, (_, &b)| c.rotate_right(1).wrapping_add(b.into()));
+
+        for i in 0..128 {
+            let offset = region + 11 * 512 + i * 4;
+
+            data[offset..(offset + 4)].copy_from_slice(&checksum.to_le_bytes());
+        }
+    }
+
+    let open = |data: &[u8]| Root::open(Image::open(Cursor::new(data.to_vec())).unwrap());
+
+    // Check the identical FATs.
+    let root = open(&data).expect("cannot open the root directory");
+
+    root.verify_fats().expect("the FATs are not identical");
+
+    // Change the entry for cluster 7 on the second FAT.
+    data[40 * 512 + 7 * 4] = 8;
+
+    let root = open(&data).expect("cannot open the root directory");
+
+    assert!(matches!(
+        root.verify_fats(),
+        Err(VerifyFatsError::Mismatch(7))
+    ));
+}
+
+#[test]
+fn open_with_options() {
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let root_offset = 48 * 512 + 3 * 4096;
+
+    // Set a volume label with 12 characters.
+    let label: Vec<u8> = "Long label12"
+        .encode_utf16()
+        .flat_map(|c| c.to_le_bytes())
+        .collect();
+
+    data[root_offset + 1] = 12;
+    data[(root_offset + 2)..(root_offset + 26)].copy_from_slice(&label);
+
+    // Add an unknown critical primary entry after file1.
+    data[root_offset + 9 * 32] = 0x86;
+
+    // Corrupt SetChecksum of file1.
+    data[root_offset + 6 * 32 + 2] ^= 0xff;
+
+    // Check each option.
+    let open = |options: exfat::OpenOptions| {
+        Root::open_with(Image::open(Cursor::new(data.clone())).unwrap(), options)
+    };
+
+    let options = exfat::OpenOptions::new();
+
+    assert!(matches!(
+        open(options.clone()),
+        Err(RootError::InvalidVolumeLabel)
+    ));
+
+    let options = options.lenient_volume_label(true);
+
+    assert!(matches!(
+        open(options.clone()),
+        Err(RootError::UnknownEntry(9, 5))
+    ));
+
+    let options = options.ignore_unknown_entries(true);
+
+    assert!(matches!(
+        open(options.clone()),
+        Err(RootError::LoadFileEntryFailed(_))
+    ));
+
+    let root = open(options.skip_checksums(true)).expect("cannot open the root directory");
+
+    assert_eq!(Some("Long label12"), root.volume_label());
+    assert_eq!(2, root.into_iter().count());
+
+    // Put an unpaired surrogate in the volume label.
+    data[root_offset + 1] = 2;
+    data[(root_offset + 2)..(root_offset + 6)].copy_from_slice(&[0x41, 0, 0x00, 0xd8]);
+
+    let open = |options: exfat::OpenOptions| {
+        Root::open_with(Image::open(Cursor::new(data.clone())).unwrap(), options)
+    };
+
+    assert!(matches!(
+        open(exfat::OpenOptions::new()),
+        Err(RootError::InvalidVolumeLabel)
+    ));
+
+    let options = exfat::OpenOptions::new()
+        .lenient_volume_label(true)
+        .ignore_unknown_entries(true)
+        .skip_checksums(true);
+    let root = open(options).expect("cannot open the root directory");
+
+    assert_eq!(Some("A\u{fffd}"), root.volume_label());
+    assert_eq!(Some(&[0x41, 0xd800][..]), root.volume_label_raw());
+}
+
+#[test]
+fn skip_benign_entries() {
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let root = 48 * 512 + 3 * 4096;
+    let dir1 = 48 * 512 + 4 * 4096;
+
+    // Add an unknown benign primary entry with a benign secondary entry after file1 and file2.
+    for offset in [root + 9 * 32, dir1 + 3 * 32] {
+        data[offset] = 0xa6;
+        data[offset + 1] = 1;
+        data[offset + 32] = 0xe6;
+    }
+
+    // Open the image.
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    let options = exfat::OpenOptions::new().on_warning({
+        let warnings = warnings.clone();
+        move |w| warnings.lock().unwrap().push(w)
+    });
+
+    let image = Image::open(Cursor::new(data)).unwrap();
+    let root = Root::open_with(image, options).expect("cannot open the root directory");
+
+    assert_eq!(2, root.root_dir().open().expect("cannot open root").len());
+
+    let items = Vec::from_iter(root);
+
+    assert_eq!(2, items.len());
+    assert_eq!(
+        [OpenWarning::UnknownEntrySkipped(9, 5)],
+        warnings.lock().unwrap().as_slice()
+    );
+
+    let dir1 = items[0].as_directory().unwrap();
+    let items = dir1.open().expect("cannot open dir1");
+
+    assert_eq!(1, items.len());
+    assert_eq!("file2", items[0].name());
+    assert_eq!(1, dir1.entry_count().unwrap());
+}
+
+#[test]
+fn seek_past_end() {
+    let image = Image::open(File::open("tests/exfat.img").unwrap()).unwrap();
+    let root = Root::open(image).expect("cannot open the root directory");
+    let mut file = root.open_file("file1").expect("cannot open file1");
+    let mut buf = [0u8; 16];
+
+    // Seek past the end.
+    assert_eq!(100, file.seek(SeekFrom::Start(100)).unwrap());
+    assert_eq!(100, file.stream_position().unwrap());
+    assert_eq!(0, file.read(&mut buf).unwrap());
+    assert_eq!(18, file.seek(SeekFrom::End(5)).unwrap());
+    assert_eq!(0, file.read(&mut buf).unwrap());
+    assert_eq!(28, file.seek(SeekFrom::Current(10)).unwrap());
+    assert_eq!(28, file.stream_position().unwrap());
+    assert_eq!(0, file.read(&mut buf).unwrap());
+    assert_eq!(20, file.seek(SeekFrom::Current(-8)).unwrap());
+
+    // Seek back.
+    assert_eq!(0, file.remaining());
+    assert_eq!(0, file.seek(SeekFrom::End(-13)).unwrap());
+    assert_eq!(13, file.remaining());
+    assert_eq!(5, file.read(&mut buf[..5]).unwrap());
+    assert_eq!(8, file.remaining());
+    assert_eq!(8, file.read(&mut buf[5..]).unwrap());
+    assert_eq!(b"Test file 1.\n", &buf[..13]);
+}
+
+#[test]
+fn check_cross_links() {
+    // Make file1 use the cluster of dir1.
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let set = 48 * 512 + 3 * 4096 + 6 * 32;
+
+    data[(set + 32 + 20)..(set + 32 + 24)].copy_from_slice(&6u32.to_le_bytes());
+
+    update_checksum(&mut data[set..(set + 96)]);
+
+    // Open the image.
+    let open = |options: exfat::OpenOptions| {
+        Root::open_with(Image::open(Cursor::new(data.clone())).unwrap(), options)
+    };
+
+    assert!(open(exfat::OpenOptions::new()).is_ok());
+    assert!(matches!(
+        open(exfat::OpenOptions::new().check_cross_links(true)),
+        Err(RootError::CrossLinkedCluster(6))
+    ));
+
+    // The original image should not have any cross-linked cluster.
+    let image = Image::open(File::open("tests/exfat.img").unwrap()).unwrap();
+    let options = exfat::OpenOptions::new().check_cross_links(true);
+
+    assert!(Root::open_with(image, options).is_ok());
+
+    // Make the FAT chain of dir1 loop.
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let set = 48 * 512 + 3 * 4096 + 3 * 32;
+
+    data[set + 32 + 1] &= !2;
+    data[(32 * 512 + 6 * 4)..(32 * 512 + 7 * 4)].copy_from_slice(&6u32.to_le_bytes());
+
+    update_checksum(&mut data[set..(set + 96)]);
+
+    let image = Image::open(Cursor::new(data)).unwrap();
+    let options = exfat::OpenOptions::new().check_cross_links(true);
+
+    assert!(matches!(
+        Root::open_with(image, options),
+        Err(RootError::ReadDirectoryFailed(
+            DirectoryError::CorruptClusterChain(6)
+        ))
+    ));
+}
+
+#[test]
+fn invalid_params() {
+    let data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let open = |offset: usize, value: u32| {
+        let mut data = data.clone();
+
+        data[offset..(offset + 4)].copy_from_slice(&value.to_le_bytes());
+
+        update_boot_checksum(&mut data);
+
+        Root::open(Image::open(Cursor::new(data)).unwrap())
+    };
+
+    // Make the FAT overlap with the cluster heap.
+    assert!(matches!(
+        open(84, 17),
+        Err(RootError::InvalidParams(ParamsError::OverlappingRegions))
+    ));
+
+    // Use ClusterCount that would overflow a 32-bit cluster index.
+    assert!(matches!(
+        open(92, 0xffffffff),
+        Err(RootError::InvalidParams(ParamsError::InvalidClusterCount))
+    ));
+
+    // Point the root directory outside the cluster heap.
+    for cluster in [1, 252] {
+        assert!(matches!(
+            open(96, cluster),
+            Err(RootError::InvalidParams(ParamsError::RootDirClusterOutOfRange(c))) if c == cluster as usize
+        ));
+    }
+}
+
+#[test]
+fn read_truncated() {
+    // Keep only the boot sector.
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+
+    data.truncate(512);
+
+    let image = Image::open(Cursor::new(data)).unwrap();
+
+    assert!(matches!(
+        Root::open(image),
+        Err(RootError::ReadMainBootFailed(0, _))
+    ));
+}
+
+#[test]
+fn image_too_small() {
+    // A partition that knows its size.
+    struct Sized(Vec<u8>);
+
+    impl DiskPartition for Sized {
+        type Err = Error;
+
+        fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Err> {
+            let data = self.0.get((offset as usize)..).unwrap_or_default();
+            let len = buf.len().min(data.len());
+
+            buf[..len].copy_from_slice(&data[..len]);
+
+            Ok(len)
+        }
+
+        fn len(&self) -> Option<u64> {
+            Some(self.0.len() as u64)
+        }
+    }
+
+    // Remove the last cluster.
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+
+    data.truncate(data.len() - 4096);
+
+    assert!(matches!(
+        Root::open(Sized(data.clone())),
+        Err(RootError::ImageTooSmall(0x100000))
+    ));
+
+    // Check the size from the built-in partitions.
+    let file = File::open(Path::new("tests").join("exfat.img")).unwrap();
+
+    assert_eq!(Some(0x100000), DiskPartition::len(&file));
+    assert_eq!(
+        Some(0xff000),
+        Image::open(Cursor::new(data.clone())).unwrap().len()
+    );
+    assert_eq!(
+        Some(0xfe000),
+        Image::open_at(Cursor::new(data), 0x1000).len()
+    );
+}
+
+#[test]
+fn read_invalid_no_fat_chain() {
+    // Set NoFatChain of file1 with DataLength larger than the volume.
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let set = 48 * 512 + 3 * 4096 + 6 * 32;
+    let len = 1u64 << 40;
+
+    data[set + 32 + 1] |= 2;
+    data[(set + 32 + 8)..(set + 32 + 16)].copy_from_slice(&len.to_le_bytes());
+    data[(set + 32 + 24)..(set + 32 + 32)].copy_from_slice(&len.to_le_bytes());
+
+    update_checksum(&mut data[set..(set + 96)]);
+
+    // Open the image.
+    let image = Image::open(Cursor::new(data)).unwrap();
+
+    assert!(matches!(
+        Root::open(image),
+        Err(RootError::CreateFileObjectFailed(6, 5, _))
+    ));
+}
+
+#[test]
+fn read_corrupt_cluster_chain() {
+    // Point the first cluster of the Up-case Table outside the FAT.
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let fat = 32 * 512;
+
+    data[(fat + 3 * 4)..(fat + 4 * 4)].copy_from_slice(&0x1234u32.to_le_bytes());
+
+    // Open the image.
+    let image = Image::open(Cursor::new(data)).unwrap();
+    let e = match Root::open(image) {
+        Err(RootError::LoadUpcaseTableFailed(e)) => e,
+        _ => panic!("unexpected result"),
+    };
+
+    assert_eq!(
+        "cluster #3 has an invalid FAT entry",
+        std::error::Error::source(&e).unwrap().to_string()
+    );
+}
+
+#[test]
+fn empty_directory() {
+    // Remove the allocation of dir1.
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let set = 48 * 512 + 3 * 4096 + 3 * 32;
+
+    data[(set + 32 + 8)..(set + 32 + 16)].fill(0);
+    data[(set + 32 + 20)..(set + 32 + 32)].fill(0);
+
+    update_checksum(&mut data[set..(set + 96)]);
+
+    // Check dir1.
+    let image = Image::open(Cursor::new(data)).unwrap();
+    let root = Root::open(image).expect("cannot open the root directory");
+    let mut dir1 = root
+        .into_iter()
+        .find_map(|i| match i {
+            Item::Directory(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+
+    assert!(dir1.open().expect("cannot open dir1").is_empty());
+    assert_eq!(0, dir1.entry_count().unwrap());
+    assert_eq!(0, dir1.free_slots().unwrap());
+    assert_eq!(0, dir1.raw_entries().unwrap().count());
+    assert!(dir1.raw_reader().unwrap().is_none());
+    assert!(dir1.deleted_entries().unwrap().is_empty());
+
+    // Create a file to allocate a cluster.
+    dir1.create_file("new file").expect("cannot create a file");
+
+    assert_eq!(1, dir1.open().expect("cannot open dir1").len());
+    assert_eq!(128 - 3, dir1.free_slots().unwrap());
+}
+
+#[test]
+fn open_with_dots() {
+    let image = Image::open(File::open(Path::new("tests").join("exfat.img")).unwrap()).unwrap();
+    let root = Root::open(image).expect("cannot open the root directory");
+
+    // Check the root directory.
+    let items = root.root_dir().open_with_dots().unwrap();
+    let names: Vec<&str> = items.iter().map(|i| i.name()).collect();
+
+    assert_eq!([".", "..", "dir1", "file1"], names.as_slice());
+    assert_eq!("/", items[1].as_directory().unwrap().path());
+
+    // Check dir1.
+    let dir1 = items[2].as_directory().unwrap();
+    let items = dir1.open_with_dots().unwrap();
+    let names: Vec<&str> = items.iter().map(|i| i.name()).collect();
+
+    assert_eq!([".", "..", "file2"], names.as_slice());
+    assert_eq!(dir1.entry_location(), items[0].entry_location());
+    assert_eq!("/", items[1].as_directory().unwrap().path());
+    assert_eq!(
+        root.root_dir().stream_entry().first_cluster(),
+        items[1].stream_entry().first_cluster()
+    );
+    assert_eq!(2, items[1].as_directory().unwrap().open().unwrap().len());
+}
+
+#[test]
+fn name_utf16() {
+    // Put an unpaired surrogate in the name of file1.
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let name = 48 * 512 + 3 * 4096 + 8 * 32 + 2;
+
+    data[(name + 8)..(name + 10)].copy_from_slice(&[0x00, 0xd8]);
+
+    // Check file1.
+    let image = Image::open(Cursor::new(data.clone())).unwrap();
+    let options = exfat::OpenOptions::new().skip_checksums(true);
+    let root = Root::open_with(image, options).expect("cannot open the root directory");
+    let file1 = root.into_iter().find(|i| !i.is_directory()).unwrap();
+
+    assert_eq!("file\u{fffd}", file1.name());
+    assert_eq!(&[0x66, 0x69, 0x6c, 0x65, 0xd800][..], file1.name_utf16());
+
+    // Check the strict mode.
+    let image = Image::open(Cursor::new(data)).unwrap();
+    let options = exfat::OpenOptions::new()
+        .skip_checksums(true)
+        .strict_names(true);
+
+    assert!(matches!(
+        Root::open_with(image, options),
+        Err(RootError::LoadFileEntryFailed(
+            FileEntryError::InvalidName {
+                cluster: 5,
+                index: 6
+            }
+        ))
+    ));
+}
+
+#[test]
+fn skip_deleted_entries() {
+    // Delete dir1, which is before file1.
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let set = 48 * 512 + 3 * 4096 + 3 * 32;
+
+    for i in 0..3 {
+        data[set + i * 32] &= 0x7f;
+    }
+
+    let open = |options: exfat::OpenOptions| {
+        let image = Image::open(Cursor::new(data.clone())).unwrap();
+
+        Root::open_with(image, options).expect("cannot open the root directory")
+    };
+
+    // The listing stops at dir1 by default.
+    let root = open(exfat::OpenOptions::new());
+
+    assert_eq!(0, root.root_dir().entry_count().unwrap());
+    assert!(root.root_dir().find("file1").unwrap().is_none());
+    assert!(root.open_file("file1").is_err());
+    assert_eq!(0, root.into_iter().count());
+
+    // Check if file1 is visible.
+    let root = open(exfat::OpenOptions::new().skip_deleted_entries(true));
+    let items = root.root_dir().open().unwrap();
+
+    assert_eq!(1, items.len());
+    assert_eq!("file1", items[0].name());
+    assert_eq!(1, root.root_dir().entry_count().unwrap());
+    assert!(root.root_dir().find("file1").unwrap().is_some());
+    assert!(root.open_file("file1").is_ok());
+    assert_eq!(1, root.into_iter().count());
+}
+
+#[test]
+fn open_read_only() {
+    // Set VolumeDirty.
+    let image = copy_image("open_read_only");
+    let mut data = std::fs::read(&image).unwrap();
+
+    data[106] |= 2;
+
+    update_boot_checksum(&mut data);
+    std::fs::write(&image, &data).unwrap();
+
+    // Open the image for writing but without the write methods.
+    let disk = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&image)
+        .expect("cannot open the image");
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    let options = exfat::OpenOptions::new().on_warning({
+        let warnings = warnings.clone();
+        move |w| warnings.lock().unwrap().push(w)
+    });
+    let root = Root::open_read_only_with(disk, options).expect("cannot open the root directory");
+
+    assert_eq!(
+        [OpenWarning::VolumeDirty],
+        warnings.lock().unwrap().as_slice()
+    );
+
+    let mut file1 = root.open_file("file1").expect("cannot open file1");
+
+    assert_eq!(b"Test file 1.\n", file1.read_to_vec().unwrap().as_slice());
+    assert_eq!(2, root.into_iter().count());
+    assert_eq!(data, std::fs::read(&image).unwrap());
+
+    std::fs::remove_file(image).unwrap();
+}
+
+#[test]
+fn item_equality() {
+    let image = Image::open(File::open(Path::new("tests").join("exfat.img")).unwrap()).unwrap();
+    let root = Root::open(image).expect("cannot open the root directory");
+
+    // Items from two listings should be the same.
+    let a = root.root_dir().open().unwrap();
+    let b = root.root_dir().open_sorted().unwrap();
+
+    assert!(a.iter().all(|i| b.contains(i)));
+    assert!(a[0] != a[1]);
+
+    #[allow(clippy::mutable_key_type)] // The hash only depends on the entry location.
+    let set: HashSet<Item<_>> = a.into_iter().chain(b).collect();
+
+    assert_eq!(2, set.len());
+
+    // Check the files and directories.
+    let dir1 = root.lookup("dir1").unwrap();
+    let dir1 = dir1.as_directory().unwrap();
+    let file2 = root.lookup("dir1/file2").unwrap();
+
+    assert!(dir1 == root.lookup("DIR1").unwrap().as_directory().unwrap());
+    assert!(file2.as_file().unwrap() == &root.open_file("dir1/file2").unwrap());
+}
+
+#[test]
+fn read_uninitialized() {
+    // Set ValidDataLength of file1 to 5.
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let set = 48 * 512 + 3 * 4096 + 6 * 32;
+
+    data[(set + 32 + 8)..(set + 32 + 16)].copy_from_slice(&5u64.to_le_bytes());
+
+    update_checksum(&mut data[set..(set + 96)]);
+
+    // Check the data.
+    let image = Image::open(Cursor::new(data)).unwrap();
+    let root = Root::open(image).expect("cannot open the root directory");
+    let mut file1 = root
+        .into_iter()
+        .find_map(|i| match i {
+            Item::File(f) => Some(f),
+            _ => None,
+        })
+        .unwrap();
+    let mut content = Vec::new();
+
+    file1.read_to_end(&mut content).expect("cannot read file1");
+
+    assert_eq!(5, file1.len());
+    assert_eq!(13, file1.allocated_len());
+    assert_eq!(b"Test \0\0\0\0\0\0\0\0", &content[..]);
+}
+
+#[test]
+fn format() {
+    for (bytes_per_sector, label) in [(512, "Formatted"), (4096, "")] {
+        // Format an empty image.
+        let image = Image::open(Cursor::new(vec![0u8; 8 * 1024 * 1024])).unwrap();
+        let opts = FormatOptions::new(8 * 1024 * 1024)
+            .bytes_per_sector(bytes_per_sector)
+            .volume_label(label);
+
+        exfat::format(&image, opts).expect("cannot format the image");
+
+        // Check the result.
+        let root = Root::open(image).expect("cannot open the root directory");
+
+        if label.is_empty() {
+            assert_eq!(None, root.volume_label());
+        } else {
+            assert_eq!(Some(label), root.volume_label());
+        }
+
+        assert_eq!(Some(0), root.percent_in_use());
+        assert_eq!(0, root.into_iter().count());
+    }
+}
+
+#[test]
+fn memory_image() {
+    // Format an image in memory.
+    let image = Arc::new(MemoryImage::new(vec![0u8; 8 * 1024 * 1024]));
+
+    exfat::format(&image, FormatOptions::new(8 * 1024 * 1024)).expect("cannot format the image");
+
+    // Update the volume then open it again.
+    let mut root = Root::open(image.clone()).expect("cannot open the root directory");
+
+    root.set_volume_label("In memory")
+        .expect("cannot set the volume label");
+
+    drop(root);
+
+    let root = Root::open(image.clone()).expect("cannot open the root directory");
+
+    assert_eq!(Some("In memory"), root.volume_label());
+
+    drop(root);
+
+    // Writing past the end should grow the data.
+    let image = Arc::into_inner(image).unwrap();
+
+    image.write_all(0x800000, &[1, 2, 3]).unwrap();
+
+    let data = image.into_inner();
+
+    assert_eq!(0x800003, data.len());
+    assert_eq!([1, 2, 3], data[0x800000..]);
+}
+
+#[test]
+fn reopen() {
+    let data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let image = Arc::new(MemoryImage::new(data));
+    let root = Root::open(image.clone()).expect("cannot open the root directory");
+
+    assert_eq!(Some("Test image"), root.volume_label());
+
+    // Change the volume label behind the root.
+    let entry = 48 * 512 + 3 * 4096;
+
+    image.write_all(entry + 1, &[2, b'O', 0, b'K', 0]).unwrap();
+
+    assert_eq!(Some("Test image"), root.volume_label());
+
+    let root = root.reopen().expect("cannot reopen the root directory");
+
+    assert_eq!(Some("OK"), root.volume_label());
+    assert_eq!(2, root.into_iter().count());
+}
+
+#[test]
+fn set_timestamps() {
+    // Open a copy of the image.
+    let image = copy_image("set_timestamps");
+    let disk = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&image)
+        .expect("cannot open the image");
+    let root = Root::open(disk).expect("cannot open the root directory");
+
+    // Update timestamps of file1 (2024-01-02 03:04:06 +01:00).
+    let ts = || Timestamp::new(0x58221883, 0, 4);
+
+    for i in root {
+        if let Item::File(mut f) = i {
+            f.set_timestamps(Timestamps::new(ts(), ts(), ts()))
+                .expect("cannot set timestamps");
+        }
+    }
+
+    // Check the updated timestamps.
+    let disk = File::open(&image).expect("cannot open the image");
+    let root = Root::open(disk).expect("cannot open the root directory");
+
+    for i in root {
+        if let Item::File(f) = i {
+            check_timestamp(f.timestamps().created(), 2, 1, 2024, 3, 4, 6, 4);
+            check_timestamp(f.timestamps().modified(), 2, 1, 2024, 3, 4, 6, 4);
+            check_timestamp(f.timestamps().accessed(), 2, 1, 2024, 3, 4, 6, 4);
+        }
+    }
+
+    std::fs::remove_file(image).unwrap();
+}
+
+#[test]
+fn keep_invalid_utc_offset() {
+    // Clear OffsetValid of the created timestamp of file1.
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let set = 48 * 512 + 3 * 4096 + 6 * 32;
+
+    data[set + 22] = 0;
+
+    update_checksum(&mut data[set..(set + 3 * 32)]);
+
+    // Update the modified timestamp only.
+    let image = Arc::new(MemoryImage::new(data));
+    let root = Root::open(image.clone()).expect("cannot open the root directory");
+    let mut file1 = root.open_file("file1").expect("cannot open file1");
+    let ts = *file1.timestamps();
+
+    assert!(!ts.created().is_utc_offset_valid());
+    assert_eq!(0, ts.created().utc_offset());
+
+    file1
+        .set_timestamps(Timestamps::new(
+            *ts.created(),
+            Timestamp::new(0x58221883, 0, 4),
+            *ts.accessed(),
+        ))
+        .expect("cannot set timestamps");
+
+    drop(file1);
+    drop(root);
+
+    // Check the fields.
+    let data = Arc::into_inner(image).unwrap().into_inner();
+
+    assert_eq!([0x00, 0x84, 0x80], data[(set + 22)..(set + 25)]);
+}
+
+#[test]
+fn compare_timestamps() {
+    // 2024-01-02 03:04:06 +01:00 and 2024-01-02 02:04:06 +00:00 are the same instant.
+    let local = Timestamp::new(0x58221883, 0, 4);
+    let utc = Timestamp::new(0x58221083, 0, 0);
+
+    assert!(local == utc);
+    assert!(Timestamp::new(0x58221883, 0, 0) > local);
+    assert!(Timestamp::new(0x58221083, 10, 0) > utc);
+    assert!(Timestamp::new(0x58221083, 100, 0) < Timestamp::new(0x58221084, 0, 0));
+    assert_eq!(Some(local.to_system_time().unwrap()), utc.to_system_time());
+
+    // Format the timestamps.
+    assert_eq!("2024-01-02T03:04:06+01:00", local.to_string());
+    assert_eq!(
+        "2024-01-02T02:04:07.500+00:00",
+        Timestamp::new(0x58221083, 150, 0).to_string()
+    );
+    assert_eq!(
+        "1980-00-00T00:00:00-00:15",
+        Timestamp::new(0, 0, -1).to_string()
+    );
+
+    // Sort by the instant.
+    let mut list = [
+        Timestamp::new(0x58221884, 0, 0),
+        local,
+        Timestamp::new(0, 0, 0),
+    ];
+
+    list.sort();
+
+    assert_eq!(
+        [
+            Timestamp::new(0, 0, 0),
+            utc,
+            Timestamp::new(0x58221884, 0, 0)
+        ],
+        list
+    );
+}
+
+#[test]
+fn negative_utc_offset() {
+    // Set the timestamps of file1 to 2024-01-02 03:04:06 with -05:00 for created and +00:00 for
+    // modified.
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let set = 48 * 512 + 3 * 4096 + 6 * 32;
+
+    data[(set + 8)..(set + 12)].copy_from_slice(&0x58221883u32.to_le_bytes());
+    data[(set + 12)..(set + 16)].copy_from_slice(&0x58221883u32.to_le_bytes());
+    data[set + 20] = 0;
+    data[set + 21] = 0;
+    data[set + 22] = 0xec;
+    data[set + 23] = 0x80;
+
+    update_checksum(&mut data[set..(set + 3 * 32)]);
+
+    // Check the timestamps.
+    let image = Image::open(Cursor::new(data)).unwrap();
+    let root = Root::open(image).expect("cannot open the root directory");
+    let file1 = root.open_file("file1").expect("cannot open file1");
+    let created = file1.timestamps().created();
+    let modified = file1.timestamps().modified();
+
+    check_timestamp(created, 2, 1, 2024, 3, 4, 6, -20);
+    check_timestamp(modified, 2, 1, 2024, 3, 4, 6, 0);
+    assert!(created > modified);
+
+    // Check the formatted timestamps.
+    assert_eq!("2024-01-02T03:04:06-05:00", created.to_string());
+    assert_eq!("2024-01-02T03:04:06+00:00", modified.to_string());
+}
+
+#[test]
+fn set_len() {
+    // Open a copy of the image.
+    let image = copy_image("set_len");
+    let open = || {
+        let disk = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&image)
+            .expect("cannot open the image");
+
+        Root::open(disk).expect("cannot open the root directory")
+    };
+
+    let file1 = |root: Root<File>| {
+        root.into_iter()
+            .find_map(|i| match i {
+                Item::File(f) => Some(f),
+                _ => None,
+            })
+            .unwrap()
+    };
+
+    // Extend file1. The next cluster is used by file2 so it need to be converted to FAT chain.
+    let mut f = file1(open());
+
+    f.set_len(10000).expect("cannot extend file1");
+
+    assert_eq!(10000, f.len());
+    assert_eq!(10000, f.allocated_len());
+    assert_eq!(3 * 4096, f.allocated_size().unwrap());
+
+    // Check the content.
+    let mut f = file1(open());
+    let mut c = Vec::new();
+
+    f.read_to_end(&mut c).expect("cannot read file1");
+
+    assert_eq!(10000, c.len());
+    assert_eq!(b"Test file 1.\n", &c[..13]);
+    assert!(c[13..].iter().all(|&b| b == 0));
+
+    // Truncate file1.
+    f.set_len(5).expect("cannot truncate file1");
+
+    let mut f = file1(open());
+    let mut c = String::new();
+
+    f.read_to_string(&mut c).expect("cannot read file1");
+
+    assert_eq!("Test ", c);
+
+    std::fs::remove_file(image).unwrap();
+}
+
+#[test]
+fn create_file() {
+    // Open a copy of the image.
+    let image = copy_image("create_file");
+    let open = || {
+        let disk = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&image)
+            .expect("cannot open the image");
+
+        let root = Root::open(disk).expect("cannot open the root directory");
+
+        root.into_iter()
+            .find_map(|i| match i {
+                Item::Directory(d) => Some(d),
+                _ => None,
+            })
+            .unwrap()
+    };
+
+    // Check invalid names.
+    let mut dir1 = open();
+
+    assert!(matches!(
+        dir1.create_file("a/b"),
+        Err(CreateError::InvalidName)
+    ));
+    assert!(matches!(
+        dir1.create_file(&"a".repeat(256)),
+        Err(CreateError::InvalidName)
+    ));
+    assert!(matches!(
+        dir1.create_file("FILE2"),
+        Err(CreateError::AlreadyExists)
+    ));
+
+    // Create enough files to fill the first cluster of dir1.
+    let mut f = dir1
+        .create_file("new file")
+        .expect("cannot create new file");
+
+    assert_eq!("new file", f.name());
+    assert_eq!(0, f.len());
+
+    // Check the position of the empty file.
+    let mut buf = [0u8; 4];
+
+    assert_eq!(5, f.seek(SeekFrom::Start(5)).unwrap());
+    assert_eq!(5, f.stream_position().unwrap());
+    assert_eq!(0, f.read(&mut buf).unwrap());
+    assert_eq!(3, f.seek(SeekFrom::Current(-2)).unwrap());
+    assert_eq!(0, f.seek(SeekFrom::End(0)).unwrap());
+    assert!(f.seek(SeekFrom::End(-1)).is_err());
+
+    for i in 0..50 {
+        dir1.create_file(&format!("file{i:03}"))
+            .expect("cannot create a file");
+    }
+
+    // Check the result.
+    let items = open().open().expect("cannot open dir1");
+    let names: Vec<&str> = items.iter().map(Item::name).collect();
+
+    assert_eq!(52, names.len());
+    assert_eq!("file2", names[0]);
+    assert_eq!("new file", names[1]);
+    assert_eq!("file049", names[51]);
+
+    let items = open().open_sorted().expect("cannot open dir1");
+    let names: Vec<&str> = items.iter().map(Item::name).collect();
+
+    assert_eq!("file000", names[0]);
+    assert_eq!("file2", names[50]);
+    assert_eq!("new file", names[51]);
+
+    std::fs::remove_file(image).unwrap();
+}
+
+#[test]
+fn create_dir() {
+    // Open a copy of the image.
+    let image = copy_image("create_dir");
+    let open = || {
+        let disk = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&image)
+            .expect("cannot open the image");
+
+        Root::open_with(disk, exfat::OpenOptions::new().check_cross_links(true))
+            .expect("cannot open the root directory")
+    };
+
+    // Check invalid names.
+    let root = open();
+    let mut dir1 = root.lookup("dir1").unwrap().as_directory().unwrap().clone();
+
+    assert!(matches!(
+        dir1.create_dir(".."),
+        Err(CreateError::InvalidName)
+    ));
+    assert!(matches!(
+        dir1.create_dir("file2"),
+        Err(CreateError::AlreadyExists)
+    ));
+
+    // Create a directory with a file.
+    let mut sub = dir1.create_dir("sub").expect("cannot create sub");
+
+    assert_eq!("/dir1/sub", sub.path());
+    assert!(sub.attributes().is_directory());
+    assert!(sub.open().unwrap().is_empty());
+    assert_eq!(128, sub.free_slots().unwrap());
+
+    sub.create_file("file3").expect("cannot create file3");
+
+    // Check the result.
+    let root = open();
+    let sub = match root.lookup("dir1/sub").unwrap() {
+        Item::Directory(d) => d,
+        Item::File(_) => unreachable!(),
+    };
+
+    assert!(sub.is_no_fat_chain());
+    assert_eq!(4096, sub.stream_entry().data_length());
+
+    let items = sub.open().expect("cannot open sub");
+
+    assert_eq!(1, items.len());
+    assert_eq!("file3", items[0].name());
+    assert!(root.lookup("dir1/sub/file3").is_ok());
+
+    std::fs::remove_file(image).unwrap();
+}
+
+#[test]
+fn read_entry_at() {
+    // Open a copy of the image.
+    let image = copy_image("read_entry_at");
+    let disk = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&image)
+        .expect("cannot open the image");
+    let root = Root::open(disk).expect("cannot open the root directory");
+
+    // Check the entries.
+    let file2 = root.lookup("dir1/file2").unwrap();
+    let (cluster, index) = file2.entry_location();
+    let meta = root.read_entry_at(cluster, index).unwrap();
+
+    assert_eq!("file2", meta.name);
+    assert_eq!(13, meta.len);
+    assert!(matches!(
+        root.read_entry_at(5, 1),
+        Err(ReadEntryError::NotFileEntry(1, 5))
+    ));
+    assert!(matches!(
+        root.read_entry_at(1000, 0),
+        Err(ReadEntryError::InvalidLocation(1000, 0))
+    ));
+
+    // Check if the changes are visible.
+    let mut file1 = root.open_file("file1").unwrap();
+    let (cluster, index) = file1.entry_location();
+
+    file1.set_len(4).unwrap();
+
+    let meta = root.read_entry_at(cluster, index).unwrap();
+
+    assert_eq!("file1", meta.name);
+    assert_eq!(4, meta.len);
+
+    std::fs::remove_file(image).unwrap();
+}
+
+#[test]
+fn rename() {
+    // Open a copy of the image.
+    let image = copy_image("rename");
+    let open = || {
+        let disk = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&image)
+            .expect("cannot open the image");
+
+        Root::open(disk).expect("cannot open the root directory")
+    };
+
+    let names = |items: Vec<Item<File>>| -> Vec<String> {
+        items.iter().map(|i| i.name().to_owned()).collect()
+    };
+
+    // Check invalid renames.
+    let mut root = open();
+
+    assert!(matches!(
+        root.rename("file3", "file4"),
+        Err(RenameError::NotFound)
+    ));
+    assert!(matches!(
+        root.rename("file1", "DIR1"),
+        Err(RenameError::AlreadyExists)
+    ));
+    assert!(matches!(
+        root.rename("file1", "file1/file2"),
+        Err(RenameError::NotDirectory)
+    ));
+    assert!(matches!(
+        root.rename("dir1", "/dir1/dir2"),
+        Err(RenameError::IntoSubtree)
+    ));
+    assert!(matches!(
+        root.rename("file1", "a?b"),
+        Err(RenameError::InvalidName)
+    ));
+
+    // Change only the case of dir1 then rename it to the same name.
+    root.rename("dir1", "Dir1").expect("cannot rename dir1");
+    root.rename("DIR1", "Dir1").expect("cannot rename dir1");
+
+    assert_eq!(vec!["Dir1", "file1"], names(open().into_iter().collect()));
+
+    // Rename file1 to a name that requires more entries.
+    let name = "a file with a name that is longer than 30 characters";
+
+    root.rename("file1", name).expect("cannot rename file1");
+
+    assert_eq!(vec!["Dir1", name], names(root.into_iter().collect()));
+
+    // Move file2 to the root.
+    let mut root = open();
+
+    root.rename("/dir1/file2", "FILE2")
+        .expect("cannot move file2");
+
+    let items: Vec<Item<File>> = open().into_iter().collect();
+    let mut file2 = items
+        .into_iter()
+        .find_map(|i| match i {
+            Item::File(f) if f.name() == "FILE2" => Some(f),
+            _ => None,
+        })
+        .unwrap();
+    let mut content = String::new();
+
+    file2.read_to_string(&mut content).unwrap();
+
+    assert_eq!("Test file 2.\n", content);
+
+    // Check dir1.
+    let dir1 = open()
+        .into_iter()
+        .find_map(|i| match i {
+            Item::Directory(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+
+    assert!(dir1.open().unwrap().is_empty());
+
+    // The old entry set of file2 should be recoverable.
+    let deleted = dir1.deleted_entries().expect("cannot read dir1");
+
+    assert_eq!(1, deleted.len());
+    assert_eq!("file2", deleted[0].name());
+    assert_eq!((6, 0), deleted[0].entry_location());
+    assert_eq!(8, deleted[0].first_cluster());
+    assert_eq!(13, deleted[0].len());
+
+    std::fs::remove_file(image).unwrap();
+}
+
+#[test]
+fn set_volume_label() {
+    // Open a copy of the image.
+    let image = copy_image("set_volume_label");
+    let open = || {
+        let disk = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&image)
+            .expect("cannot open the image");
+
+        Root::open(disk).expect("cannot open the root directory")
+    };
+
+    // Check invalid label.
+    let mut root = open();
+
+    assert!(matches!(
+        root.set_volume_label("Label is too long"),
+        Err(SetVolumeLabelError::TooLong)
+    ));
+
+    // Change the label.
+    root.set_volume_label("New label").unwrap();
+
+    assert_eq!(Some("New label"), root.volume_label());
+
+    let mut root = open();
+
+    assert_eq!(Some("New label"), root.volume_label());
+    assert_eq!(9, root.volume_label_raw().unwrap().len());
+
+    // Remove the label.
+    root.set_volume_label("").unwrap();
+
+    assert_eq!(None, root.volume_label());
+
+    let root = open();
+
+    assert_eq!(None, root.volume_label());
+    assert_eq!(2, root.into_iter().count());
+
+    // Set the label again.
+    let mut root = open();
+
+    root.set_volume_label("Ünïcode").unwrap();
+
+    let root = open();
+
+    assert_eq!(Some("Ünïcode"), root.volume_label());
+    assert_eq!(2, root.into_iter().count());
+
+    std::fs::remove_file(image).unwrap();
+}
+
+#[test]
+fn multi_cluster_directory() {
+    // Open a copy of the image.
+    let image = copy_image("multi_cluster_directory");
+    let open = || {
+        let disk = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&image)
+            .expect("cannot open the image");
+
+        let root = Root::open(disk).expect("cannot open the root directory");
+
+        root.into_iter()
+            .find_map(|i| match i {
+                Item::Directory(d) => Some(d),
+                _ => None,
+            })
+            .unwrap()
+    };
+
+    // Allocate a cluster for a file in between each extension of dir1 so the clusters of dir1 are
+    // not contiguous.
+    let mut dir1 = open();
+
+    for i in 0..150 {
+        let mut f = dir1
+            .create_file(&format!("file{i:03}"))
+            .expect("cannot create a file");
+
+        if i % 40 == 0 {
+            f.set_len(1).expect("cannot allocate a cluster");
+        }
+    }
+
+    // Check the extents of a file that has a non-contiguous cluster.
+    let mut f = dir1.find("file000").unwrap().unwrap();
+    let f = match &mut f {
+        Item::File(f) => f,
+        Item::Directory(_) => unreachable!(),
+    };
+
+    f.set_len(8192).expect("cannot grow a file");
+
+    let extents = f.extents().unwrap();
+
+    assert_eq!(2, extents.len());
+    assert_eq!(8192, extents.iter().map(|e| e.1).sum::<u64>());
+
+    // Check the clusters.
+    let dir1 = open();
+    let mut clusters: Vec<usize> = dir1
+        .raw_entries()
+        .expect("cannot read dir1")
+        .map(|e| e.expect("cannot read an entry").cluster())
+        .collect();
+
+    clusters.dedup();
+
+    assert_eq!(4, clusters.len());
+    assert!(clusters.windows(2).any(|w| w[1] != w[0] + 1));
+
+    // Check the items.
+    let items = dir1.open().expect("cannot open dir1");
+
+    assert_eq!(151, items.len());
+    assert_eq!(151, dir1.entry_count().unwrap());
+    assert_eq!("file2", items[0].name());
+    assert_eq!("file149", items[150].name());
+
+    std::fs::remove_file(image).unwrap();
+}
+
+#[test]
+fn full_directory() {
+    // Fill all free entries of a directory so there is no end of directory entry. A name longer
+    // than 15 characters requires 4 entries.
+    fn fill(dir: &mut Directory<File>) -> usize {
+        let mut count = 0;
+
+        loop {
+            let free = dir.free_slots().unwrap();
+            let name = if free == 0 {
+                break;
+            } else if free % 3 == 0 {
+                format!("f{count:03}")
+            } else {
+                format!("long-file-name-{count:03}")
+            };
+
+            dir.create_file(&name).expect("cannot create a file");
+            count += 1;
+        }
+
+        count
+    }
+
+    // Open a copy of the image.
+    let image = copy_image("full_directory");
+    let open = || {
+        let disk = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&image)
+            .expect("cannot open the image");
+
+        Root::open(disk).expect("cannot open the root directory")
+    };
+
+    let dir1 = |root: Root<File>| {
+        root.into_iter()
+            .find_map(|i| match i {
+                Item::Directory(d) => Some(d),
+                _ => None,
+            })
+            .unwrap()
+    };
+
+    // Fill the only cluster of dir1.
+    let count = fill(&mut dir1(open())) + 1;
+    let dir = dir1(open());
+    let items = dir.open().expect("cannot open dir1");
+
+    assert_eq!(128, dir.raw_entries().unwrap().count());
+    assert_eq!(count, items.len());
+    assert_eq!(count, dir.entry_count().unwrap());
+    assert!(dir.find("f040").unwrap().is_some());
+
+    // Fill the root directory.
+    let count = fill(&mut open().root_dir()) + 2;
+    let root = open();
+
+    assert_eq!(128, root.raw_entries().unwrap().count());
+    assert_eq!(
+        count,
+        root.root_dir().open().expect("cannot open root").len()
+    );
+    assert_eq!(count, root.into_iter().count());
+
+    std::fs::remove_file(image).unwrap();
+}
+
+#[test]
+fn cluster_cache() {
+    // Open a copy of the image with the cache enabled.
+    let image = copy_image("cluster_cache");
+    let disk = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&image)
+        .expect("cannot open the image");
+
+    let options = exfat::OpenOptions::new().cache_size(4);
+    let root = Root::open_with(disk, options).expect("cannot open the root directory");
+    let (mut dir1, mut file1) = (None, None);
+
+    for i in root {
+        match i {
+            Item::Directory(d) => dir1 = Some(d),
+            Item::File(f) => file1 = Some(f),
+        }
+    }
+
+    // Read twice so the second read come from the cache.
+    let mut dir1 = dir1.unwrap();
+    let mut file1 = file1.unwrap();
+
+    for _ in 0..2 {
+        assert_eq!(b"Test file 1.\n", file1.read_to_vec().unwrap().as_slice());
+        assert_eq!(1, dir1.open().expect("cannot open dir1").len());
+    }
+
+    // Check if the cache is updated when writing.
+    dir1.create_file("new file").expect("cannot create a file");
+    file1.set_len(4).expect("cannot truncate file1");
+
+    assert_eq!(2, dir1.open().expect("cannot open dir1").len());
+    assert_eq!(b"Test", file1.read_to_vec().unwrap().as_slice());
+
+    std::fs::remove_file(image).unwrap();
+}
+
+#[test]
+fn readahead() {
+    // A partition that counts the reads.
+    struct Counted(File, Arc<AtomicUsize>);
+
+    impl DiskPartition for Counted {
+        type Err = Error;
+
+        fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Err> {
+            self.1.fetch_add(1, Ordering::Relaxed);
+            DiskPartition::read(&self.0, offset, buf)
+        }
+    }
+
+    // Grow file1 to 4 clusters.
+    let image = copy_image("readahead");
+    let disk = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&image)
+        .expect("cannot open the image");
+    let root = Root::open(disk).expect("cannot open the root directory");
+
+    for i in root {
+        if let Item::File(mut f) = i {
+            f.set_len(0x4000).expect("cannot grow file1");
+        }
+    }
+
+    // Read with small buffers.
+    let reads = Arc::new(AtomicUsize::new(0));
+    let disk = File::open(&image).expect("cannot open the image");
+    let root = Root::open(Counted(disk, reads.clone())).expect("cannot open the root directory");
+    let file1 = root
+        .into_iter()
+        .find_map(|i| match i {
+            Item::File(f) => Some(f),
+            _ => None,
+        })
+        .unwrap();
+    let expected = file1.clone_reader().read_to_vec().unwrap();
+    let extents = file1.extents().unwrap().len();
+    let mut file1 = file1.with_readahead(4);
+    let mut data = Vec::new();
+    let mut buf = [0u8; 512];
+
+    reads.store(0, Ordering::Relaxed);
+
+    loop {
+        let n = Read::read(&mut file1, &mut buf).unwrap();
+
+        if n == 0 {
+            break;
+        }
+
+        data.extend_from_slice(&buf[..n]);
+    }
+
+    assert_eq!(expected, data);
+    assert_eq!(b"Test file 1.\n", &data[..13]);
+    assert_eq!(extents, reads.load(Ordering::Relaxed));
+
+    // Check a random read.
+    file1.seek(SeekFrom::Start(0x1000)).unwrap();
+    file1.read_exact(&mut buf).unwrap();
+
+    assert_eq!([0u8; 512], buf);
+
+    std::fs::remove_file(image).unwrap();
+}
+
+#[test]
+fn read_typed() {
+    // A partition that can be made to fail.
+    struct Flaky(File, Arc<AtomicBool>);
+
+    impl DiskPartition for Flaky {
+        type Err = Error;
+
+        fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Err> {
+            if self.1.load(Ordering::Relaxed) {
+                Err(Error::from(ErrorKind::TimedOut))
+            } else {
+                DiskPartition::read(&self.0, offset, buf)
+            }
+        }
+    }
+
+    // Open file1.
+    let fail = Arc::new(AtomicBool::new(false));
+    let disk = File::open(Path::new("tests").join("exfat.img")).unwrap();
+    let root = Root::open(Flaky(disk, fail.clone())).expect("cannot open the root directory");
+    let mut file1 = root.open_file("file1").expect("cannot open file1");
+    let mut buf = [0u8; 16];
+
+    // Check the error.
+    fail.store(true, Ordering::Relaxed);
+
+    match file1.read_typed(&mut buf) {
+        Err(TypedReadError::ReadFailed(_, e)) => assert_eq!(ErrorKind::TimedOut, e.kind()),
+        _ => panic!("unexpected result"),
+    }
+
+    // Retry.
+    fail.store(false, Ordering::Relaxed);
+
+    assert_eq!(13, file1.read_typed(&mut buf).unwrap());
+    assert_eq!(b"Test file 1.\n", &buf[..13]);
+}
+
+#[test]
+fn partial_reads() {
+    // A partition that returns only one byte for each read.
+    struct Trickle(Vec<u8>);
+
+    impl DiskPartition for Trickle {
+        type Err = Error;
+
+        fn read(&self, offset: u64, buf: &mut [u8]) -> Result<usize, Self::Err> {
+            let data = usize::try_from(offset)
+                .ok()
+                .and_then(|o| self.0.get(o..))
+                .unwrap_or_default();
+
+            match (data.first(), buf.first_mut()) {
+                (Some(&b), Some(v)) => {
+                    *v = b;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    let data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let disk = Trickle(data.clone());
+
+    // Check read_exact directly.
+    let mut buf = vec![0u8; 1000];
+
+    disk.read_exact(3, &mut buf).unwrap();
+
+    assert_eq!(&data[3..1003], buf);
+
+    let e = disk
+        .read_exact(data.len() as u64 - 4, &mut buf)
+        .unwrap_err();
+
+    assert_eq!(ErrorKind::UnexpectedEof, e.kind());
+
+    // Check the files.
+    let root = Root::open(disk).expect("cannot open the root directory");
+    let mut file1 = root.open_file("file1").expect("cannot open file1");
+    let mut content = String::new();
+
+    file1.read_to_string(&mut content).unwrap();
+
+    assert_eq!("Test file 1.\n", content);
+
+    let mut file2 = root.open_file("dir1/file2").expect("cannot open file2");
+
+    content.clear();
+    file2.read_to_string(&mut content).unwrap();
+
+    assert_eq!("Test file 2.\n", content);
+}
+
+#[test]
+fn hash_volume() {
+    let hash = |data: &[u8], mode: HashMode| {
+        let root = Root::open(MemoryImage::new(data.to_vec())).unwrap();
+        let mut hasher = DefaultHasher::new();
+
+        root.hash_volume(&mut hasher, mode).unwrap();
+        hasher.finish()
+    };
+
+    let mut data = std::fs::read(Path::new("tests").join("exfat.img")).unwrap();
+    let volume = hash(&data, HashMode::Volume);
+    let clusters = hash(&data, HashMode::AllocatedClusters);
+
+    assert_ne!(volume, clusters);
+
+    // A free cluster should not change the hash.
+    let heap = 48 * 512;
+
+    data[heap + 20 * 4096] = 0xff;
+
+    assert_eq!(volume, hash(&data, HashMode::Volume));
+    assert_eq!(clusters, hash(&data, HashMode::AllocatedClusters));
+
+    // Change the data of file1.
+    data[heap + 5 * 4096] = b't';
+
+    assert_ne!(volume, hash(&data, HashMode::Volume));
+    assert_ne!(clusters, hash(&data, HashMode::AllocatedClusters));
+}
+
+/// Updates SetChecksum of the entry set in `set`.
+fn update_boot_checksum(data: &mut [u8]) {
+    let checksum = data[..(11 * 512)]
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != 106 && i != 107 && i != 112)
+        .fold(0u32, |c, (_, &b)| c.rotate_right(1).wrapping_add(b.into()));
+
+    for i in 0..128 {
+        let offset = 11 * 512 + i * 4;
+
+        data[offset..(offset + 4)].copy_from_slice(&checksum.to_le_bytes());
+    }
+}
+
+fn update_checksum(set: &mut [u8]) {
+    let checksum = set
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != 2 && i != 3)
+        .fold(0u16, |c, (_, &b)| c.rotate_right(1).wrapping_add(b.into()));
+
+    set[2..4].copy_from_slice(&checksum.to_le_bytes());
+}
+
+fn copy_image(name: &str) -> PathBuf {
+    let src = Path::new("tests").join("exfat.img");
+    let dst = std::env::temp_dir().join(format!("exfat-{}-{name}.img", std::process::id()));
+
+    std::fs::copy(src, &dst).expect("cannot copy exfat.img");
+
+    dst
+}